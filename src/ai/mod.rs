@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use std::sync::{Arc, atomic::AtomicBool};
+
+mod resources;
+mod systems;
+
+use resources::*;
+use systems::*;
+
+use crate::{
+    engine::{search::TABLE_MEGABYTES, transposition::TranspositionTable},
+    game::MoveSet,
+    states::AppState,
+};
+
+pub struct AiPlugin;
+
+// Computer players. The search runs on the async compute pool so the
+// window keeps going while it thinks, the result comes back as a MovePiece
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AiTask {
+            task: None,
+            board: None,
+            table: Arc::new(TranspositionTable::new(TABLE_MEGABYTES)),
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+        .add_systems(
+            Update,
+            (cancel_ai_move, start_ai_move, finish_ai_move)
+                .chain()
+                .before(MoveSet)
+                .run_if(in_state(AppState::Playing)),
+        );
+    }
+}
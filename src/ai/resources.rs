@@ -0,0 +1,19 @@
+use bevy::{prelude::*, tasks::Task};
+use std::sync::{Arc, atomic::AtomicBool};
+
+use crate::engine::{board::Board, search::SearchResult, transposition::TranspositionTable};
+
+// Seconds the computer may think for one move at most
+pub const MAX_THINK_TIME: f32 = 10.0;
+
+// The search that's currently running (if any) and the position it's for.
+// Tasks can't be reflected so this one doesn't show up in the inspector.
+// table stays between moves, the searches share it with the task.
+// stop is set to end the running search, a new one gets a new flag
+#[derive(Resource)]
+pub struct AiTask {
+    pub task: Option<Task<Option<SearchResult>>>,
+    pub board: Option<Board>,
+    pub table: Arc<TranspositionTable>,
+    pub stop: Arc<AtomicBool>,
+}
@@ -0,0 +1,171 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, futures::check_ready},
+};
+
+use crate::{
+    ai::resources::*,
+    clock::resources::{ChessClock, IncrementMode},
+    engine::{
+        board::{Board, tile},
+        search::{SearchLimits, search_with, time_budget},
+    },
+    game::{
+        components::PieceAnimation,
+        resources::{GameSetup, GameState, MoveHistory, MovePiece, NewGame, PlayerKind},
+    },
+    pieces::components::{ChessPiece, PieceColor},
+    settings::resources::Settings,
+};
+
+// A new game throws away whatever the computer was thinking about.
+// Dropping the task doesn't stop the search threads, the stop flag does.
+// The positions from the last game won't come up again
+pub fn cancel_ai_move(
+    mut events: EventReader<NewGame>,
+    game_state: Res<GameState>,
+    mut ai_task: ResMut<AiTask>,
+) {
+    // a move was taken back while it was thinking, finish_ai_move throws the result away
+    if ai_task.board.is_some_and(|board| board != game_state.board) {
+        ai_task.stop.store(true, Ordering::Relaxed);
+    }
+
+    if events.read().count() > 0 {
+        ai_task.stop.store(true, Ordering::Relaxed);
+        ai_task.task = None;
+        ai_task.board = None;
+        ai_task.table.clear();
+    }
+}
+
+// With a clock the search decides from the time that's left,
+// without one it gets a fixed amount of time
+fn think_time(clock: &ChessClock, color: PieceColor) -> Duration {
+    let max = Duration::from_secs_f32(MAX_THINK_TIME);
+
+    if !clock.enabled {
+        return max;
+    }
+
+    let remaining = Duration::from_secs_f32(clock.remaining(color).max(0.0));
+    let increment = Duration::from_secs_f32(clock.time_control.increment);
+
+    let budget = match clock.time_control.mode {
+        IncrementMode::Fischer => time_budget(remaining, increment),
+        // the delay never adds to the clock, but thinking that long doesn't cost anything
+        IncrementMode::Bronstein | IncrementMode::SimpleDelay => {
+            time_budget(remaining, Duration::ZERO) + increment
+        }
+    };
+
+    budget.min(max)
+}
+
+pub fn start_ai_move(
+    setup: Res<GameSetup>,
+    game_state: Res<GameState>,
+    clock: Res<ChessClock>,
+    settings: Res<Settings>,
+    animations: Query<(), With<PieceAnimation>>,
+    history: Res<MoveHistory>,
+    mut ai_task: ResMut<AiTask>,
+) {
+    if ai_task.task.is_some()
+        || game_state.is_over()
+        || setup.player(game_state.turn) != PlayerKind::Computer
+    {
+        return;
+    }
+
+    // wait for the last move to finish sliding, looks weird otherwise
+    if !animations.is_empty() {
+        return;
+    }
+
+    let board = game_state.board;
+    let limits = SearchLimits {
+        depth: setup.computer_depth,
+        time: Some(think_time(&clock, game_state.turn)),
+        threads: settings.threads,
+    };
+
+    println!(
+        "Computer is thinking (depth {}, {:.1}s, {} threads)",
+        limits.depth,
+        limits.time.map_or(0.0, |time| time.as_secs_f32()),
+        limits.threads
+    );
+
+    // going back to a position from the game is a draw
+    let played: Vec<u64> = history
+        .positions
+        .iter()
+        .take(history.cursor + 1)
+        .map(Board::hash)
+        .collect();
+
+    let table = ai_task.table.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    ai_task.stop = stop.clone();
+    ai_task.board = Some(board);
+    ai_task.task = Some(
+        AsyncComputeTaskPool::get()
+            .spawn(async move { search_with(&board, &played, limits, &table, &stop, |_| {}) }),
+    );
+}
+
+pub fn finish_ai_move(
+    mut ai_task: ResMut<AiTask>,
+    game_state: Res<GameState>,
+    query: Query<(Entity, &ChessPiece)>,
+    mut move_piece_events: EventWriter<MovePiece>,
+) {
+    let Some(task) = ai_task.task.as_mut() else {
+        return;
+    };
+
+    let Some(result) = check_ready(task) else {
+        return;
+    };
+
+    let board = ai_task.board.take();
+    ai_task.task = None;
+
+    // the position changed while it was thinking, so the move is useless now
+    if board != Some(game_state.board) {
+        return;
+    }
+
+    let Some(result) = result else {
+        return;
+    };
+
+    println!(
+        "Computer plays {:?} (score {}, depth {}, {} nodes)",
+        result.best_move, result.score, result.depth, result.nodes
+    );
+
+    let mv = result.best_move;
+
+    let from = tile(mv.from);
+
+    if let Some((entity, _)) = query.iter().find(|(_, p)| p.position == from) {
+        move_piece_events.write(MovePiece {
+            piece: entity,
+            from,
+            to: tile(mv.to),
+            promotion: mv.promotion,
+            computer: true,
+        });
+    }
+}
@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+
+pub mod resources;
+mod systems;
+
+use resources::*;
+use systems::*;
+
+use crate::{game::MoveSet, states::AppState};
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        let time_control = TimeControlPreset::Rapid.time_control(IncrementMode::Fischer);
+
+        app.register_type::<ChessClock>()
+            .register_type::<TimeControl>()
+            .insert_resource(ChessClock::new(time_control))
+            .add_systems(Update, reset_clock.before(MoveSet))
+            .add_systems(
+                Update,
+                (switch_clock, tick_clock)
+                    .chain()
+                    .after(MoveSet)
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(
+                EguiContextPass,
+                clock_ui.run_if(in_state(AppState::Playing).or(in_state(AppState::GameOver))),
+            );
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::pieces::components::PieceColor;
+
+pub const CLOCK_PANEL_WIDTH: f32 = 160.0;
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeControlPreset {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+// What happens with the increment after a move
+// Fischer: always added
+// Bronstein: added back, but never more than the time actually used for the move
+// SimpleDelay: the clock only starts running after the delay is used up
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IncrementMode {
+    Fischer,
+    Bronstein,
+    SimpleDelay,
+}
+
+// base and increment are in seconds
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub base: f32,
+    pub increment: f32,
+    pub mode: IncrementMode,
+}
+
+impl TimeControlPreset {
+    pub const ALL: [TimeControlPreset; 4] = [
+        TimeControlPreset::Bullet,
+        TimeControlPreset::Blitz,
+        TimeControlPreset::Rapid,
+        TimeControlPreset::Classical,
+    ];
+
+    pub fn time_control(&self, mode: IncrementMode) -> TimeControl {
+        let (minutes, increment) = match self {
+            TimeControlPreset::Bullet => (1.0, 1.0),
+            TimeControlPreset::Blitz => (5.0, 3.0),
+            TimeControlPreset::Rapid => (10.0, 5.0),
+            TimeControlPreset::Classical => (30.0, 20.0),
+        };
+
+        TimeControl {
+            base: minutes * 60.0,
+            increment,
+            mode,
+        }
+    }
+}
+
+// Remaining time for both sides (in seconds)
+// The clock only starts once white made the first move
+// enabled is false for games without a time control
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ChessClock {
+    pub enabled: bool,
+    pub white: f32,
+    pub black: f32,
+    pub time_control: TimeControl,
+    pub running: bool,
+    // time spent on the current move, needed for Bronstein
+    pub turn_spent: f32,
+    // what's left of the simple delay for the current move
+    pub delay_left: f32,
+}
+
+impl ChessClock {
+    pub fn new(time_control: TimeControl) -> ChessClock {
+        ChessClock {
+            enabled: true,
+            white: time_control.base,
+            black: time_control.base,
+            time_control,
+            running: false,
+            turn_spent: 0.0,
+            delay_left: 0.0,
+        }
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> f32 {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    pub fn remaining_mut(&mut self, color: PieceColor) -> &mut f32 {
+        match color {
+            PieceColor::White => &mut self.white,
+            PieceColor::Black => &mut self.black,
+        }
+    }
+}
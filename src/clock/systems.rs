@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    clock::resources::*,
+    game::resources::{BoardOrientation, GameSetup, GameState, MoveMade, NewGame},
+    pieces::components::{ChessPiece, PieceColor, PieceType},
+};
+
+// After every move the side that just moved gets its increment
+// and the other side's clock starts running
+pub fn switch_clock(
+    mut event_move_made: EventReader<MoveMade>,
+    mut clock: ResMut<ChessClock>,
+    game_state: Res<GameState>,
+) {
+    for MoveMade { .. } in event_move_made.read() {
+        // turn was already switched, so the one who moved is the other color
+        let mover = game_state.turn.opposite();
+        let increment = clock.time_control.increment;
+        let spent = clock.turn_spent;
+
+        match clock.time_control.mode {
+            IncrementMode::Fischer => *clock.remaining_mut(mover) += increment,
+            IncrementMode::Bronstein => *clock.remaining_mut(mover) += spent.min(increment),
+            IncrementMode::SimpleDelay => {}
+        }
+
+        clock.turn_spent = 0.0;
+        clock.delay_left = match clock.time_control.mode {
+            IncrementMode::SimpleDelay => increment,
+            _ => 0.0,
+        };
+        clock.running = true;
+    }
+}
+
+// New games use the time control from the setup screen
+pub fn reset_clock(
+    mut events: EventReader<NewGame>,
+    mut clock: ResMut<ChessClock>,
+    setup: Res<GameSetup>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    *clock = ChessClock::new(setup.time_control.unwrap_or(clock.time_control));
+    clock.enabled = setup.time_control.is_some();
+}
+
+pub fn tick_clock(
+    time: Res<Time>,
+    mut clock: ResMut<ChessClock>,
+    mut game_state: ResMut<GameState>,
+    query: Query<&ChessPiece>,
+) {
+    if !clock.enabled || !clock.running || game_state.is_over() {
+        return;
+    }
+
+    let mut delta = time.delta_secs();
+    clock.turn_spent += delta;
+
+    // simple delay gets used up before the actual clock moves
+    if clock.delay_left > 0.0 {
+        let used = clock.delay_left.min(delta);
+        clock.delay_left -= used;
+        delta -= used;
+    }
+
+    let turn = game_state.turn;
+    let remaining = clock.remaining_mut(turn);
+    *remaining = (*remaining - delta).max(0.0);
+
+    if *remaining <= 0.0 {
+        println!("{:?} ran out of time!", turn);
+
+        let pieces: Vec<&ChessPiece> = query.iter().collect();
+
+        game_state.timeout = true;
+        game_state.insufficient_material = !has_mating_material(&pieces, &turn.opposite());
+    }
+}
+
+// Very rough check like most chess sites do it. A lone king, king + bishop and
+// king + knight can never force mate, everything else counts as enough material
+fn has_mating_material(pieces: &[&ChessPiece], color: &PieceColor) -> bool {
+    let others: Vec<&&ChessPiece> = pieces
+        .iter()
+        .filter(|p| p.color == *color && p.piece != PieceType::King)
+        .collect();
+
+    match others.as_slice() {
+        [] => false,
+        [only] => !matches!(only.piece, PieceType::Bishop | PieceType::Knight),
+        _ => true,
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    // tenths only matter when it's getting close
+    if seconds < 10.0 {
+        format!("0:{:04.1}", seconds)
+    } else {
+        let total = seconds.ceil() as u32;
+        format!("{}:{:02}", total / 60, total % 60)
+    }
+}
+
+// Clocks are on the left of the board, the one at the top belongs to
+// whoever is at the top of the board right now
+pub fn clock_ui(
+    mut contexts: EguiContexts,
+    clock: Res<ChessClock>,
+    game_state: Res<GameState>,
+    orientation: Res<BoardOrientation>,
+) {
+    if !clock.enabled {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let (top, bottom) = if orientation.flipped {
+        (PieceColor::White, PieceColor::Black)
+    } else {
+        (PieceColor::Black, PieceColor::White)
+    };
+
+    egui::SidePanel::left("clocks")
+        .resizable(false)
+        .exact_width(CLOCK_PANEL_WIDTH)
+        .show(ctx, |ui| {
+            clock_label(ui, &clock, &game_state, top);
+
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                clock_label(ui, &clock, &game_state, bottom);
+            });
+        });
+}
+
+fn clock_label(ui: &mut egui::Ui, clock: &ChessClock, game_state: &GameState, color: PieceColor) {
+    let remaining = clock.remaining(color);
+    let active = clock.running && game_state.turn == color && !game_state.is_over();
+
+    let mut text = egui::RichText::new(format_time(remaining))
+        .size(36.0)
+        .monospace();
+
+    if remaining <= 0.0 {
+        text = text.color(egui::Color32::RED);
+    } else if active {
+        text = text.strong().color(egui::Color32::WHITE);
+    } else {
+        text = text.color(egui::Color32::GRAY);
+    }
+
+    ui.label(format!("{:?}", color));
+    ui.label(text);
+}
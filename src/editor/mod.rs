@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+
+pub mod resources;
+mod systems;
+
+use resources::*;
+use systems::*;
+
+use crate::{engine::board::Board, states::AppState};
+
+pub struct EditorPlugin;
+
+// Setting up a custom position before playing or analysing it.
+// The board itself is the normal one, the editor just keeps sending NewGame
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BoardEditor>()
+            .register_type::<EditorTool>()
+            .insert_resource(BoardEditor {
+                board: Board::start(),
+                tool: EditorTool::Hand,
+                holding: None,
+                palette_drag: None,
+                fen: String::new(),
+                fen_error: None,
+            })
+            .add_systems(OnEnter(AppState::Editor), enter_editor)
+            .add_systems(OnExit(AppState::Editor), leave_editor)
+            .add_systems(
+                Update,
+                (editor_input, drag_held_piece)
+                    .chain()
+                    .run_if(in_state(AppState::Editor)),
+            )
+            .add_systems(
+                EguiContextPass,
+                editor_ui.run_if(in_state(AppState::Editor)),
+            );
+    }
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::engine::board::{Board, Piece};
+
+// What a left click on the board does in the editor
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum EditorTool {
+    // pick up pieces that are already on the board, dropping them off the board removes them
+    #[default]
+    Hand,
+    // puts this piece on the clicked tile, clicking the same piece again takes it away
+    Place(Piece),
+    Erase,
+}
+
+// The position being set up. Every change gets sent as a NewGame
+// so the real pieces on the board always show it
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct BoardEditor {
+    pub board: Board,
+    pub tool: EditorTool,
+    // piece picked up with the hand tool and the tile it came from
+    pub holding: Option<(Entity, (u8, u8))>,
+    // piece dragged out of the palette, it goes where the mouse gets let go
+    pub palette_drag: Option<Piece>,
+    // what's in the FEN field, only read back when enter is pressed
+    pub fen: String,
+    pub fen_error: Option<String>,
+}
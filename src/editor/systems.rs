@@ -0,0 +1,372 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use super::resources::*;
+use crate::{
+    engine::board::{Board, Piece, square},
+    game::{
+        cursor_world_position,
+        resources::{BoardLayout, DRAG_Z, GameSetup, NewGame, SIDE_PANEL_WIDTH},
+    },
+    pieces::components::{ChessPiece, PieceColor, PieceType},
+    states::AppState,
+    theme::resources::Themes,
+};
+
+const PALETTE: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+// The editor picks up where it was left, the board still has whatever game was on it before
+pub fn enter_editor(mut editor: ResMut<BoardEditor>, mut new_game: EventWriter<NewGame>) {
+    editor.fen = editor.board.to_fen();
+    editor.fen_error = None;
+
+    new_game.write(NewGame {
+        board: editor.board,
+    });
+}
+
+pub fn leave_editor(mut editor: ResMut<BoardEditor>) {
+    editor.holding = None;
+    editor.palette_drag = None;
+}
+
+// Puts a piece on a tile (or clears it with None). The en passant square
+// can't be right anymore once something was moved by hand
+fn set_tile(board: &mut Board, tile: (u8, u8), piece: Option<Piece>) {
+    board.squares[square(tile) as usize] = piece;
+    board.en_passant = None;
+}
+
+// Castling rights for a king or rook that isn't on its square anymore just go away
+fn remove_impossible_castling(board: &mut Board) {
+    let white_king_side = board.castling_possible(PieceColor::White, true);
+    let white_queen_side = board.castling_possible(PieceColor::White, false);
+    let black_king_side = board.castling_possible(PieceColor::Black, true);
+    let black_queen_side = board.castling_possible(PieceColor::Black, false);
+
+    board.castling.white_king_side &= white_king_side;
+    board.castling.white_queen_side &= white_queen_side;
+    board.castling.black_king_side &= black_king_side;
+    board.castling.black_queen_side &= black_queen_side;
+}
+
+// Sends the edited position to the board and the FEN field
+fn show_edit(editor: &mut BoardEditor, new_game: &mut EventWriter<NewGame>) {
+    remove_impossible_castling(&mut editor.board);
+
+    editor.fen = editor.board.to_fen();
+    editor.fen_error = None;
+
+    new_game.write(NewGame {
+        board: editor.board,
+    });
+}
+
+// Clicks and drops on the board. Right click always removes the piece under the mouse
+pub fn editor_input(
+    q_window: Query<&Window>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    layout: Res<BoardLayout>,
+    mut editor: ResMut<BoardEditor>,
+    q_pieces: Query<(Entity, &ChessPiece)>,
+    mut new_game: EventWriter<NewGame>,
+) {
+    let tile = cursor_world_position(&q_window, &q_camera)
+        .and_then(|world_position| layout.world_to_tile(world_position));
+
+    let editor = &mut *editor;
+    let mut changed = false;
+
+    // piece from the palette let go somewhere, off the board it just disappears
+    if buttons.just_released(MouseButton::Left) {
+        let palette_drag = editor.palette_drag.take();
+
+        if let (Some(piece), Some(tile)) = (palette_drag, tile) {
+            set_tile(&mut editor.board, tile, Some(piece));
+            changed = true;
+        }
+    }
+
+    if let (true, Some(tile)) = (buttons.just_pressed(MouseButton::Right), tile) {
+        set_tile(&mut editor.board, tile, None);
+        changed = true;
+    }
+
+    match editor.tool {
+        EditorTool::Hand => {
+            if buttons.just_pressed(MouseButton::Left) {
+                editor.holding = tile.and_then(|tile| {
+                    q_pieces
+                        .iter()
+                        .find(|(_, piece)| piece.position == tile)
+                        .map(|(entity, _)| (entity, tile))
+                });
+            }
+
+            let released = buttons
+                .just_released(MouseButton::Left)
+                .then(|| editor.holding.take())
+                .flatten();
+
+            if let Some((_, from)) = released {
+                let piece = editor.board.piece_at(square(from));
+
+                set_tile(&mut editor.board, from, None);
+
+                if let Some(to) = tile {
+                    set_tile(&mut editor.board, to, piece);
+                }
+
+                // even dropped back on the same tile, the sprite has to go back into place
+                changed = true;
+            }
+        }
+        EditorTool::Place(piece) => {
+            if let (true, Some(tile)) = (buttons.just_pressed(MouseButton::Left), tile) {
+                let current = editor.board.piece_at(square(tile));
+                let new = if current == Some(piece) {
+                    None
+                } else {
+                    Some(piece)
+                };
+
+                set_tile(&mut editor.board, tile, new);
+                changed = true;
+            }
+        }
+        EditorTool::Erase => {
+            if let (true, Some(tile)) = (buttons.just_pressed(MouseButton::Left), tile) {
+                set_tile(&mut editor.board, tile, None);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        show_edit(editor, &mut new_game);
+    }
+}
+
+// Same as drag_piece in the game, the held sprite follows the cursor
+pub fn drag_held_piece(
+    q_window: Query<&Window>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    editor: Res<BoardEditor>,
+    mut query: Query<&mut Transform, With<ChessPiece>>,
+) {
+    let Some((entity, _)) = editor.holding else {
+        return;
+    };
+
+    if let (Some(world_position), Ok(mut transform)) = (
+        cursor_world_position(&q_window, &q_camera),
+        query.get_mut(entity),
+    ) {
+        transform.translation.x = world_position.x;
+        transform.translation.y = world_position.y;
+        transform.translation.z = DRAG_Z;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn editor_ui(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<BoardEditor>,
+    themes: Res<Themes>,
+    asset_server: Res<AssetServer>,
+    mut setup: ResMut<GameSetup>,
+    mut new_game: EventWriter<NewGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // the piece sprites are already loaded for the board, egui just needs ids for them
+    let palette: Vec<(Piece, egui::TextureId)> = [PieceColor::White, PieceColor::Black]
+        .into_iter()
+        .flat_map(|color| PALETTE.map(|kind| Piece { kind, color }))
+        .map(|piece| {
+            let image = asset_server.load(themes.current().piece_image(piece.kind, piece.color));
+            (piece, contexts.add_image(image))
+        })
+        .collect();
+
+    let ctx = contexts.ctx_mut();
+    let editor = &mut *editor;
+    let before = editor.board;
+
+    if editor.palette_drag.is_some() {
+        ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
+    }
+
+    egui::SidePanel::right("board_editor")
+        .resizable(false)
+        .exact_width(SIDE_PANEL_WIDTH)
+        .show(ctx, |ui| {
+            ui.heading("Board editor");
+            ui.label("Click or drag pieces onto the board, right click removes them");
+            ui.add_space(5.0);
+
+            for row in palette.chunks(PALETTE.len()) {
+                ui.horizontal_wrapped(|ui| {
+                    for (piece, texture) in row {
+                        let image = egui::Image::new(egui::load::SizedTexture::new(
+                            *texture,
+                            egui::vec2(24.0, 24.0),
+                        ));
+                        let response = ui.add(
+                            egui::Button::image(image)
+                                .selected(editor.tool == EditorTool::Place(*piece))
+                                .sense(egui::Sense::click_and_drag()),
+                        );
+
+                        if response.clicked() {
+                            editor.tool = EditorTool::Place(*piece);
+                        }
+
+                        if response.drag_started() {
+                            editor.tool = EditorTool::Place(*piece);
+                            editor.palette_drag = Some(*piece);
+                        }
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                if ui
+                    .selectable_label(editor.tool == EditorTool::Hand, "Move")
+                    .clicked()
+                {
+                    editor.tool = EditorTool::Hand;
+                }
+
+                if ui
+                    .selectable_label(editor.tool == EditorTool::Erase, "Remove")
+                    .clicked()
+                {
+                    editor.tool = EditorTool::Erase;
+                }
+            });
+
+            ui.separator();
+            ui.label("Side to move");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut editor.board.turn, PieceColor::White, "White");
+                ui.radio_value(&mut editor.board.turn, PieceColor::Black, "Black");
+            });
+
+            ui.label("Castling");
+
+            // only the ones where the king and rook are still at home can be ticked
+            let white_king_side = editor.board.castling_possible(PieceColor::White, true);
+            let white_queen_side = editor.board.castling_possible(PieceColor::White, false);
+            let black_king_side = editor.board.castling_possible(PieceColor::Black, true);
+            let black_queen_side = editor.board.castling_possible(PieceColor::Black, false);
+            let castling = &mut editor.board.castling;
+
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    white_king_side,
+                    egui::Checkbox::new(&mut castling.white_king_side, "White O-O"),
+                );
+                ui.add_enabled(
+                    white_queen_side,
+                    egui::Checkbox::new(&mut castling.white_queen_side, "O-O-O"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    black_king_side,
+                    egui::Checkbox::new(&mut castling.black_king_side, "Black O-O"),
+                );
+                ui.add_enabled(
+                    black_queen_side,
+                    egui::Checkbox::new(&mut castling.black_queen_side, "O-O-O"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Start position").clicked() {
+                    editor.board = Board::start();
+                }
+
+                if ui.button("Clear").clicked() {
+                    let turn = editor.board.turn;
+
+                    editor.board = Board::empty();
+                    editor.board.turn = turn;
+                }
+            });
+
+            ui.separator();
+            ui.label("FEN");
+
+            let response = ui.text_edit_singleline(&mut editor.fen);
+
+            if response.changed() {
+                editor.fen_error = None;
+            }
+
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match Board::from_fen(editor.fen.trim()) {
+                    Ok(board) => editor.board = board,
+                    Err(error) => editor.fen_error = Some(error),
+                }
+            }
+
+            if let Some(error) = &editor.fen_error {
+                ui.colored_label(egui::Color32::RED, format!("Invalid FEN: {}", error));
+            }
+
+            if ui.button("Copy FEN").clicked() {
+                ui.ctx().copy_text(editor.board.to_fen());
+            }
+
+            ui.separator();
+
+            // the position can only be used once there's nothing wrong with it
+            let problems = editor.board.problems();
+
+            if problems.is_empty() {
+                ui.colored_label(egui::Color32::GREEN, "Position is valid");
+            }
+
+            for problem in &problems {
+                ui.colored_label(egui::Color32::LIGHT_RED, problem);
+            }
+
+            ui.add_space(10.0);
+
+            if ui
+                .add_enabled(problems.is_empty(), egui::Button::new("Play from here"))
+                .clicked()
+            {
+                // the game setup screen picks the players and clock, starting from this FEN
+                setup.start_fen = editor.board.to_fen();
+                next_state.set(AppState::GameSetup);
+            }
+
+            if ui
+                .add_enabled(problems.is_empty(), egui::Button::new("Analyze"))
+                .clicked()
+            {
+                new_game.write(NewGame {
+                    board: editor.board,
+                });
+                next_state.set(AppState::Analysis);
+            }
+
+            if ui.button("Main menu").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+        });
+
+    if editor.board != before {
+        show_edit(editor, &mut new_game);
+    }
+}
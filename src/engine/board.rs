@@ -0,0 +1,503 @@
+use bevy::prelude::*;
+
+use crate::{
+    engine::fen::square_name,
+    pieces::components::{PieceColor, PieceType},
+};
+
+// Squares go from 0 (a1) to 63 (h8), file = square % 8 and rank = square / 8.
+// The rest of the game uses (file, rank) tiles starting at 1, these convert between both
+pub type Square = u8;
+
+pub fn square(tile: (u8, u8)) -> Square {
+    (tile.1 - 1) * 8 + (tile.0 - 1)
+}
+
+pub fn tile(square: Square) -> (u8, u8) {
+    (square % 8 + 1, square / 8 + 1)
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Piece {
+    pub kind: PieceType,
+    pub color: PieceColor,
+}
+
+// The rook files are where the castling rooks started, (king side, queen side).
+// h and a in normal chess, anywhere on the back rank in chess960
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+    pub white_rook_files: (u8, u8),
+    pub black_rook_files: (u8, u8),
+}
+
+impl Default for CastlingRights {
+    fn default() -> CastlingRights {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+            white_rook_files: (7, 0),
+            black_rook_files: (7, 0),
+        }
+    }
+}
+
+impl CastlingRights {
+    pub fn allowed(&self, color: PieceColor, king_side: bool) -> bool {
+        match (color, king_side) {
+            (PieceColor::White, true) => self.white_king_side,
+            (PieceColor::White, false) => self.white_queen_side,
+            (PieceColor::Black, true) => self.black_king_side,
+            (PieceColor::Black, false) => self.black_queen_side,
+        }
+    }
+
+    pub fn set(&mut self, color: PieceColor, king_side: bool, allowed: bool) {
+        match (color, king_side) {
+            (PieceColor::White, true) => self.white_king_side = allowed,
+            (PieceColor::White, false) => self.white_queen_side = allowed,
+            (PieceColor::Black, true) => self.black_king_side = allowed,
+            (PieceColor::Black, false) => self.black_queen_side = allowed,
+        }
+    }
+
+    pub fn rook_file(&self, color: PieceColor, king_side: bool) -> u8 {
+        let (king_side_file, queen_side_file) = match color {
+            PieceColor::White => self.white_rook_files,
+            PieceColor::Black => self.black_rook_files,
+        };
+
+        if king_side {
+            king_side_file
+        } else {
+            queen_side_file
+        }
+    }
+
+    pub fn set_rook_file(&mut self, color: PieceColor, king_side: bool, file: u8) {
+        let files = match color {
+            PieceColor::White => &mut self.white_rook_files,
+            PieceColor::Black => &mut self.black_rook_files,
+        };
+
+        if king_side {
+            files.0 = file;
+        } else {
+            files.1 = file;
+        }
+    }
+}
+
+// a1 for white, a8 for black
+pub fn back_rank(color: PieceColor) -> Square {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 56,
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+}
+
+// The whole position, everything a FEN string has in it.
+// Small enough to just copy it around, make_move returns a new board
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub squares: [Option<Piece>; 64],
+    pub turn: PieceColor,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+impl Default for Board {
+    fn default() -> Board {
+        Board::start()
+    }
+}
+
+pub const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+pub const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+pub const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+pub const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+// Square next to `square` in the given direction, None if that's off the board
+pub fn offset(square: Square, (df, dr): (i8, i8)) -> Option<Square> {
+    let file = (square % 8) as i8 + df;
+    let rank = (square / 8) as i8 + dr;
+
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some((rank * 8 + file) as Square)
+    } else {
+        None
+    }
+}
+
+impl Board {
+    pub fn empty() -> Board {
+        Board {
+            squares: [None; 64],
+            turn: PieceColor::White,
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn start() -> Board {
+        Board::from_fen(super::fen::STARTING_FEN).expect("the starting FEN is valid")
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        self.squares[square as usize]
+    }
+
+    // Every piece on the board together with its square
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.squares
+            .iter()
+            .enumerate()
+            .filter_map(|(square, piece)| piece.map(|p| (square as Square, p)))
+    }
+
+    pub fn king_square(&self, color: PieceColor) -> Option<Square> {
+        self.pieces()
+            .find(|(_, p)| p.kind == PieceType::King && p.color == color)
+            .map(|(square, _)| square)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.king_square(self.turn)
+            .is_some_and(|king| self.is_attacked(king, self.turn.opposite()))
+    }
+
+    // Whether any piece of color `by` attacks the square
+    pub fn is_attacked(&self, square: Square, by: PieceColor) -> bool {
+        let is = |target: Option<Square>, kinds: &[PieceType]| {
+            target
+                .and_then(|t| self.piece_at(t))
+                .is_some_and(|p| p.color == by && kinds.contains(&p.kind))
+        };
+
+        // pawns attack diagonally forward, so look backwards from the square
+        let pawn_rank = match by {
+            PieceColor::White => -1,
+            PieceColor::Black => 1,
+        };
+
+        if is(offset(square, (-1, pawn_rank)), &[PieceType::Pawn])
+            || is(offset(square, (1, pawn_rank)), &[PieceType::Pawn])
+        {
+            return true;
+        }
+
+        if KNIGHT_OFFSETS
+            .iter()
+            .any(|o| is(offset(square, *o), &[PieceType::Knight]))
+        {
+            return true;
+        }
+
+        if KING_OFFSETS
+            .iter()
+            .any(|o| is(offset(square, *o), &[PieceType::King]))
+        {
+            return true;
+        }
+
+        let slides = [
+            (ROOK_DIRECTIONS, [PieceType::Rook, PieceType::Queen]),
+            (BISHOP_DIRECTIONS, [PieceType::Bishop, PieceType::Queen]),
+        ];
+
+        for (directions, kinds) in slides {
+            for direction in directions {
+                let mut current = square;
+
+                while let Some(next) = offset(current, direction) {
+                    if let Some(piece) = self.piece_at(next) {
+                        if piece.color == by && kinds.contains(&piece.kind) {
+                            return true;
+                        }
+                        break;
+                    }
+                    current = next;
+                }
+            }
+        }
+
+        false
+    }
+
+    pub fn is_en_passant(&self, mv: Move) -> bool {
+        self.en_passant == Some(mv.to)
+            && self
+                .piece_at(mv.from)
+                .is_some_and(|p| p.kind == PieceType::Pawn)
+            && mv.from % 8 != mv.to % 8
+    }
+
+    // Castling is written as the king taking its own rook, that's the only way to tell
+    // every chess960 castling apart from normal king moves (the king can even stay where it is)
+    pub fn is_castling(&self, mv: Move) -> bool {
+        match (self.piece_at(mv.from), self.piece_at(mv.to)) {
+            (Some(king), Some(rook)) => {
+                king.kind == PieceType::King
+                    && rook.kind == PieceType::Rook
+                    && king.color == rook.color
+            }
+            _ => false,
+        }
+    }
+
+    // Where the king and the rook end up when castling, g and f or c and d
+    pub fn castling_targets(&self, mv: Move) -> (Square, Square) {
+        let rank = mv.from / 8 * 8;
+
+        if mv.to > mv.from {
+            (rank + 6, rank + 5)
+        } else {
+            (rank + 2, rank + 3)
+        }
+    }
+
+    // The square the moving piece ends up on, only differs from `to` for castling
+    pub fn destination(&self, mv: Move) -> Square {
+        if self.is_castling(mv) {
+            self.castling_targets(mv).0
+        } else {
+            mv.to
+        }
+    }
+
+    // Castling that can't be written the normal way (king on e1 / e8 moving two squares),
+    // UCI engines have to be told about it
+    pub fn is_chess960(&self) -> bool {
+        [PieceColor::White, PieceColor::Black]
+            .into_iter()
+            .any(|color| {
+                [true, false].into_iter().any(|king_side| {
+                    let standard_file = if king_side { 7 } else { 0 };
+
+                    self.castling.allowed(color, king_side)
+                        && (self.king_square(color) != Some(back_rank(color) + 4)
+                            || self.castling.rook_file(color, king_side) != standard_file)
+                })
+            })
+    }
+
+    // Square of the piece that gets taken, only differs from `to` for en passant
+    pub fn captured_square(&self, mv: Move) -> Square {
+        if self.is_en_passant(mv) {
+            (mv.from / 8) * 8 + mv.to % 8
+        } else {
+            mv.to
+        }
+    }
+
+    pub fn captured_piece(&self, mv: Move) -> Option<Piece> {
+        self.piece_at(self.captured_square(mv))
+            .filter(|p| p.color != self.turn)
+    }
+
+    // Castling rights only make sense while the king and that rook haven't left the back rank,
+    // with the king between the two rooks
+    pub fn castling_possible(&self, color: PieceColor, king_side: bool) -> bool {
+        let rook = back_rank(color) + self.castling.rook_file(color, king_side);
+
+        let has_rook = self.piece_at(rook)
+            == Some(Piece {
+                kind: PieceType::Rook,
+                color,
+            });
+
+        has_rook
+            && self
+                .king_square(color)
+                .is_some_and(|king| king / 8 == rook / 8 && (rook > king) == king_side)
+    }
+
+    // Everything that makes the position impossible to play from, empty when it's fine
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for color in [PieceColor::White, PieceColor::Black] {
+            let kings = self
+                .pieces()
+                .filter(|(_, p)| p.kind == PieceType::King && p.color == color)
+                .count();
+
+            if kings != 1 {
+                problems.push(format!("{:?} needs exactly one king", color));
+            }
+        }
+
+        if self
+            .pieces()
+            .any(|(square, p)| p.kind == PieceType::Pawn && (square / 8 == 0 || square / 8 == 7))
+        {
+            problems.push("Pawns can't be on the first or last rank".to_string());
+        }
+
+        // the side that just moved can't have left its own king in check
+        let waiting = self.turn.opposite();
+
+        if self
+            .king_square(waiting)
+            .is_some_and(|king| self.is_attacked(king, self.turn))
+        {
+            problems.push(format!("{:?} is in check but it's not their move", waiting));
+        }
+
+        for (allowed, color, king_side) in [
+            (self.castling.white_king_side, PieceColor::White, true),
+            (self.castling.white_queen_side, PieceColor::White, false),
+            (self.castling.black_king_side, PieceColor::Black, true),
+            (self.castling.black_queen_side, PieceColor::Black, false),
+        ] {
+            if allowed && !self.castling_possible(color, king_side) {
+                problems.push(format!(
+                    "{:?} can't castle {} side, the king or rook isn't on its square",
+                    color,
+                    if king_side { "king" } else { "queen" }
+                ));
+            }
+        }
+
+        // the en passant square is the one a pawn of the side that just moved skipped over
+        if let Some(square) = self.en_passant {
+            let (rank, pawn, start) = match self.turn {
+                PieceColor::White => (5, square.wrapping_sub(8), square + 8),
+                PieceColor::Black => (2, square + 8, square.wrapping_sub(8)),
+            };
+
+            let pawn_moved = Some(Piece {
+                kind: PieceType::Pawn,
+                color: self.turn.opposite(),
+            });
+
+            // the rank goes first, the other squares are only on the board when it's right
+            if square / 8 != rank
+                || self.piece_at(pawn) != pawn_moved
+                || self.piece_at(square).is_some()
+                || self.piece_at(start).is_some()
+            {
+                problems.push(format!(
+                    "There can't be an en passant capture on {}",
+                    square_name(square)
+                ));
+            }
+        }
+
+        problems
+    }
+
+    // For positions that are about to be played from, only the first problem
+    // since the places that show it have room for one line
+    pub fn playable(self) -> Result<Board, String> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(self),
+        }
+    }
+
+    // Plays the move and returns the new position. Doesn't check if the move is legal
+    pub fn make_move(&self, mv: Move) -> Board {
+        let mut board = *self;
+
+        let Some(piece) = self.piece_at(mv.from) else {
+            return board;
+        };
+
+        let is_capture = self.captured_piece(mv).is_some();
+
+        if self.is_castling(mv) {
+            // both leave first, in chess960 one can land where the other one was
+            let (king_to, rook_to) = self.castling_targets(mv);
+            let rook = board.squares[mv.to as usize].take();
+
+            board.squares[mv.from as usize] = None;
+            board.squares[king_to as usize] = Some(piece);
+            board.squares[rook_to as usize] = rook;
+        } else {
+            board.squares[self.captured_square(mv) as usize] = None;
+            board.squares[mv.from as usize] = None;
+            board.squares[mv.to as usize] = Some(Piece {
+                kind: mv.promotion.unwrap_or(piece.kind),
+                color: piece.color,
+            });
+        }
+
+        board.en_passant = None;
+        if piece.kind == PieceType::Pawn && mv.from.abs_diff(mv.to) == 16 {
+            board.en_passant = Some((mv.from + mv.to) / 2);
+        }
+
+        // moving the king or a rook (or taking a rook) loses castling rights
+        for color in [PieceColor::White, PieceColor::Black] {
+            for king_side in [true, false] {
+                let rook = back_rank(color) + self.castling.rook_file(color, king_side);
+
+                if (piece.kind == PieceType::King && piece.color == color)
+                    || mv.from == rook
+                    || mv.to == rook
+                {
+                    board.castling.set(color, king_side, false);
+                }
+            }
+        }
+
+        if piece.kind == PieceType::Pawn || is_capture {
+            board.halfmove_clock = 0;
+        } else {
+            board.halfmove_clock += 1;
+        }
+
+        if self.turn == PieceColor::Black {
+            board.fullmove_number += 1;
+        }
+
+        board.turn = self.turn.opposite();
+        board
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{Receiver, RecvTimeoutError, channel},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{
+        board::{Board, Move},
+        notation::parse_move,
+        search::Score,
+    },
+    pieces::components::PieceColor,
+};
+
+// How long the engine gets to answer uci / isready, and to come up with a move
+// once the move time is over. After that it's taken for hung and killed
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Another engine (Stockfish or anything else that speaks UCI) running as a child process.
+// Commands go into its stdin and the answers come back line by line from its stdout,
+// read on their own thread so waiting for them can give up
+pub struct ExternalEngine {
+    // what it was started from, so a changed path in the settings starts the new one
+    pub path: String,
+    // what the engine calls itself ("id name ...")
+    pub name: String,
+    // whether UCI_Chess960 is on, castling moves are written differently then
+    chess960: bool,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl ExternalEngine {
+    pub fn start(path: &str) -> Result<ExternalEngine, String> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("couldn't start {}: {}", path, e))?;
+
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(format!("couldn't talk to {}", path));
+        };
+
+        // stops by itself once the engine quits and stdout closes
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = ExternalEngine {
+            path: path.to_string(),
+            name: path.to_string(),
+            chess960: false,
+            child,
+            stdin,
+            lines,
+        };
+
+        engine.send("uci")?;
+
+        let deadline = Instant::now() + ANSWER_TIMEOUT;
+
+        // the engine introduces itself and lists its options before uciok
+        loop {
+            let line = engine.read_line(deadline)?;
+
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+
+        engine.send("isready")?;
+        engine.wait_for("readyok", Instant::now() + ANSWER_TIMEOUT)?;
+
+        println!("Started {} ({})", engine.name, path);
+
+        Ok(engine)
+    }
+
+    // Searches the position for that long. Gives back the best move
+    // and the last score the engine reported on the way there
+    pub fn go(
+        &mut self,
+        board: &Board,
+        milliseconds: u64,
+    ) -> Result<(Option<Move>, Option<Score>), String> {
+        if board.is_chess960() != self.chess960 {
+            self.chess960 = board.is_chess960();
+            self.send(&format!(
+                "setoption name UCI_Chess960 value {}",
+                self.chess960
+            ))?;
+        }
+
+        self.send(&format!("position fen {}", board.to_fen()))?;
+        self.send(&format!("go movetime {}", milliseconds))?;
+
+        let deadline = Instant::now() + Duration::from_millis(milliseconds) + ANSWER_TIMEOUT;
+        let mut score = None;
+
+        loop {
+            let line = self.read_line(deadline)?;
+            let mut words = line.split_whitespace();
+
+            match words.next() {
+                Some("info") => {
+                    if let Some(new_score) = parse_score(&line, board.turn) {
+                        score = Some(new_score);
+                    }
+                }
+                // "bestmove (none)" when there's nothing to play, that just doesn't parse
+                Some("bestmove") => {
+                    let best = words.next().and_then(|text| parse_move(board, text).ok());
+                    return Ok((best, score));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("{} stopped listening: {}", self.name, e))
+    }
+
+    // An engine that doesn't answer in time is killed, whoever gets the Err starts a new one
+    fn read_line(&mut self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{} quit", self.name)),
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = self.child.kill();
+                Err(format!("{} stopped answering", self.name))
+            }
+        }
+    }
+
+    fn wait_for(&mut self, answer: &str, deadline: Instant) -> Result<(), String> {
+        while self.read_line(deadline)?.trim() != answer {}
+        Ok(())
+    }
+}
+
+// Engines keep running after the game closes unless they're told to stop
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// "info depth 20 ... score cp 35 ..." or "score mate -3", UCI scores are from the side to move
+fn parse_score(line: &str, turn: PieceColor) -> Option<Score> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let i = words.iter().position(|w| *w == "score")?;
+    let value: i32 = words.get(i + 2)?.parse().ok()?;
+
+    let sign = match turn {
+        PieceColor::White => 1,
+        PieceColor::Black => -1,
+    };
+
+    match *words.get(i + 1)? {
+        "cp" => Some(Score::Centipawns(sign * value)),
+        "mate" if value == 0 => Some(Score::Checkmated(turn)),
+        "mate" => Some(Score::Mate(sign * value)),
+        _ => None,
+    }
+}
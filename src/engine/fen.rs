@@ -0,0 +1,258 @@
+use crate::{
+    engine::board::{Board, Piece, Square, back_rank},
+    pieces::components::{PieceColor, PieceType},
+};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let kind = match c.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+
+    let color = if c.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+
+    Some(Piece { kind, color })
+}
+
+fn piece_to_char(piece: Piece) -> char {
+    let c = match piece.kind {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+
+    match piece.color {
+        PieceColor::White => c.to_ascii_uppercase(),
+        PieceColor::Black => c,
+    }
+}
+
+pub fn parse_square(text: &str) -> Option<Square> {
+    let mut chars = text.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+
+    Some((rank as u8 - b'1') * 8 + (file as u8 - b'a'))
+}
+
+pub fn square_name(square: Square) -> String {
+    format!("{}{}", (b'a' + square % 8) as char, square / 8 + 1)
+}
+
+// K and Q mean the rook furthest out on that side of the king (X-FEN),
+// which is h / a in normal chess
+fn outer_rook_file(board: &Board, color: PieceColor, king_side: bool) -> u8 {
+    let rank = back_rank(color);
+    let corner = if king_side { 7 } else { 0 };
+
+    let Some(king) = board
+        .king_square(color)
+        .filter(|king| *king / 8 == rank / 8)
+    else {
+        return corner;
+    };
+
+    let is_rook = |file: &u8| {
+        board.piece_at(rank + file)
+            == Some(Piece {
+                kind: PieceType::Rook,
+                color,
+            })
+    };
+
+    let file = if king_side {
+        (king % 8 + 1..8).rev().find(is_rook)
+    } else {
+        (0..king % 8).find(is_rook)
+    };
+
+    file.unwrap_or(corner)
+}
+
+impl Board {
+    // The move counters are optional since a lot of FENs found online leave them out.
+    // Only the text is checked, not the position (that's Board::playable), so the editor can load a broken one
+    pub fn from_fen(fen: &str) -> Result<Board, String> {
+        let mut board = Board::empty();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+
+        if fields.len() < 2 {
+            return Err("a FEN needs at least the pieces and the side to move".to_string());
+        }
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("expected 8 ranks but found {}", ranks.len()));
+        }
+
+        // FEN starts with rank 8
+        for (i, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file = 0u8;
+
+            for c in rank_text.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    // checked before adding, a long run like "99999999" would overflow the u8
+                    if file as u32 + empty > 8 {
+                        return Err(format!("rank {} has too many squares", rank + 1));
+                    }
+
+                    file += empty as u8;
+                } else {
+                    let piece =
+                        piece_from_char(c).ok_or_else(|| format!("unknown piece '{}'", c))?;
+
+                    if file > 7 {
+                        return Err(format!("rank {} has too many squares", rank + 1));
+                    }
+
+                    board.squares[(rank * 8 + file) as usize] = Some(piece);
+                    file += 1;
+                }
+            }
+
+            if file != 8 {
+                return Err(format!("rank {} doesn't have 8 squares", rank + 1));
+            }
+        }
+
+        board.turn = match fields[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            other => return Err(format!("unknown side to move '{}'", other)),
+        };
+
+        // KQkq, or the rook files for chess960 ("HAha", Shredder-FEN)
+        if let Some(castling) = fields.get(2).filter(|s| **s != "-") {
+            for c in castling.chars() {
+                let color = if c.is_ascii_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+
+                let (king_side, file) = match c.to_ascii_lowercase() {
+                    'k' => (true, outer_rook_file(&board, color, true)),
+                    'q' => (false, outer_rook_file(&board, color, false)),
+                    letter @ 'a'..='h' => {
+                        let file = letter as u8 - b'a';
+                        let king_file = board.king_square(color).map(|king| king % 8);
+
+                        (king_file.map_or(file >= 4, |king| file > king), file)
+                    }
+                    _ => return Err(format!("unknown castling right '{}'", c)),
+                };
+
+                board.castling.set(color, king_side, true);
+                board.castling.set_rook_file(color, king_side, file);
+            }
+        }
+
+        if let Some(en_passant) = fields.get(3).filter(|s| **s != "-") {
+            board.en_passant = Some(
+                parse_square(en_passant)
+                    .ok_or_else(|| format!("invalid en passant square '{}'", en_passant))?,
+            );
+        }
+
+        if let Some(halfmove) = fields.get(4) {
+            board.halfmove_clock = halfmove
+                .parse()
+                .map_err(|_| format!("invalid halfmove clock '{}'", halfmove))?;
+        }
+
+        if let Some(fullmove) = fields.get(5) {
+            board.fullmove_number = fullmove
+                .parse()
+                .map_err(|_| format!("invalid move number '{}'", fullmove))?;
+        }
+
+        Ok(board)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+
+            for file in 0..8 {
+                match self.piece_at(rank * 8 + file) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_to_char(piece));
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        // rooks that didn't start in the corners (chess960) are written as their file
+        let mut castling: String = [
+            (PieceColor::White, true),
+            (PieceColor::White, false),
+            (PieceColor::Black, true),
+            (PieceColor::Black, false),
+        ]
+        .into_iter()
+        .filter(|(color, king_side)| self.castling.allowed(*color, *king_side))
+        .map(|(color, king_side)| {
+            let c = match (king_side, self.castling.rook_file(color, king_side)) {
+                (true, 7) => 'k',
+                (false, 0) => 'q',
+                (_, file) => (b'a' + file) as char,
+            };
+
+            match color {
+                PieceColor::White => c.to_ascii_uppercase(),
+                PieceColor::Black => c,
+            }
+        })
+        .collect();
+
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            match self.turn {
+                PieceColor::White => "w",
+                PieceColor::Black => "b",
+            },
+            castling,
+            self.en_passant.map_or("-".to_string(), square_name),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
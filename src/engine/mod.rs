@@ -0,0 +1,14 @@
+// The chess rules on their own, no entities or systems in here.
+// The game plugin keeps a Board around and asks it which moves are legal,
+// the computer players search on copies of it
+pub mod board;
+pub mod eval;
+pub mod external;
+pub mod fen;
+pub mod movegen;
+pub mod notation;
+pub mod perft;
+pub mod search;
+pub mod transposition;
+pub mod variants;
+pub mod zobrist;
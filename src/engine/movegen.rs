@@ -0,0 +1,212 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    engine::board::{
+        BISHOP_DIRECTIONS, Board, KING_OFFSETS, KNIGHT_OFFSETS, Move, ROOK_DIRECTIONS, Square,
+        back_rank, offset,
+    },
+    pieces::components::{PieceColor, PieceType},
+};
+
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+impl Board {
+    // Every legal move for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let after = self.make_move(*mv);
+
+                after
+                    .king_square(self.turn)
+                    .is_none_or(|king| !after.is_attacked(king, after.turn))
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves().contains(&mv)
+    }
+
+    // The legal move for two clicked squares or a typed "e2e4", pawns become a queen unless
+    // something else is asked for. Castling can also be given as where the king ends up (e1g1),
+    // a normal move goes first since in chess960 that square can be a normal king move too
+    pub fn find_move(
+        &self,
+        from: Square,
+        to: Square,
+        promotion: Option<PieceType>,
+    ) -> Option<Move> {
+        let legal = self.legal_moves();
+
+        legal
+            .iter()
+            .find(|m| {
+                m.from == from
+                    && m.to == to
+                    && (m.promotion.is_none()
+                        || m.promotion == Some(promotion.unwrap_or(PieceType::Queen)))
+            })
+            .or_else(|| {
+                legal.iter().find(|m| {
+                    m.from == from && self.is_castling(**m) && self.castling_targets(**m).0 == to
+                })
+            })
+            .copied()
+    }
+
+    // Moves that follow how the pieces move but might leave the own king in check
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(48);
+
+        for (from, piece) in self.pieces() {
+            if piece.color != self.turn {
+                continue;
+            }
+
+            match piece.kind {
+                PieceType::Pawn => self.pawn_moves(from, &mut moves),
+                PieceType::Knight => self.step_moves(from, &KNIGHT_OFFSETS, &mut moves),
+                PieceType::Bishop => self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves),
+                PieceType::Rook => self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves),
+                PieceType::Queen => {
+                    self.slide_moves(from, &BISHOP_DIRECTIONS, &mut moves);
+                    self.slide_moves(from, &ROOK_DIRECTIONS, &mut moves);
+                }
+                PieceType::King => {
+                    self.step_moves(from, &KING_OFFSETS, &mut moves);
+                    self.castling_moves(from, &mut moves);
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn is_empty(&self, square: Square) -> bool {
+        self.piece_at(square).is_none()
+    }
+
+    fn is_enemy(&self, square: Square) -> bool {
+        self.piece_at(square).is_some_and(|p| p.color != self.turn)
+    }
+
+    fn step_moves(&self, from: Square, offsets: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for to in offsets.iter().filter_map(|o| offset(from, *o)) {
+            if self.is_empty(to) || self.is_enemy(to) {
+                moves.push(Move::new(from, to));
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Square, directions: &[(i8, i8)], moves: &mut Vec<Move>) {
+        for direction in directions {
+            let mut current = from;
+
+            while let Some(to) = offset(current, *direction) {
+                if self.is_empty(to) {
+                    moves.push(Move::new(from, to));
+                } else {
+                    if self.is_enemy(to) {
+                        moves.push(Move::new(from, to));
+                    }
+                    break;
+                }
+                current = to;
+            }
+        }
+    }
+
+    fn pawn_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let (forward, start_rank, last_rank) = match self.turn {
+            PieceColor::White => (1, 1, 7),
+            PieceColor::Black => (-1, 6, 0),
+        };
+
+        // reaching the last rank is one move per piece it can turn into
+        let mut push = |to: Square| {
+            if to / 8 == last_rank {
+                for kind in PROMOTIONS {
+                    moves.push(Move {
+                        from,
+                        to,
+                        promotion: Some(kind),
+                    });
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        if let Some(one) = offset(from, (0, forward)).filter(|s| self.is_empty(*s)) {
+            push(one);
+
+            // two squares from the starting rank
+            if let Some(two) =
+                offset(one, (0, forward)).filter(|s| from / 8 == start_rank && self.is_empty(*s))
+            {
+                push(two);
+            }
+        }
+
+        for side in [-1, 1] {
+            if let Some(to) = offset(from, (side, forward))
+                .filter(|s| self.is_enemy(*s) || self.en_passant == Some(*s))
+            {
+                push(to);
+            }
+        }
+    }
+
+    // Chess960 castling, normal chess is just one of its setups. The king goes to g / c and
+    // the rook next to it on f / d. Nothing else can be between where they start and where they
+    // end up, and the king can't start in, pass through or land in check
+    fn castling_moves(&self, from: Square, moves: &mut Vec<Move>) {
+        let enemy = self.turn.opposite();
+        let rank = back_rank(self.turn);
+
+        if from / 8 != rank / 8 {
+            return;
+        }
+
+        for king_side in [true, false] {
+            if !self.castling.allowed(self.turn, king_side) {
+                continue;
+            }
+
+            let rook = rank + self.castling.rook_file(self.turn, king_side);
+
+            let has_rook = self
+                .piece_at(rook)
+                .is_some_and(|p| p.kind == PieceType::Rook && p.color == self.turn);
+
+            if !has_rook || (rook > from) != king_side {
+                continue;
+            }
+
+            let mv = Move::new(from, rook);
+            let (king_to, rook_to) = self.castling_targets(mv);
+
+            let blocked = between(from, king_to)
+                .chain(between(rook, rook_to))
+                .any(|square| square != from && square != rook && !self.is_empty(square));
+
+            if blocked || between(from, king_to).any(|square| self.is_attacked(square, enemy)) {
+                continue;
+            }
+
+            moves.push(mv);
+        }
+    }
+}
+
+// Both squares and everything between them on the same rank
+fn between(a: Square, b: Square) -> RangeInclusive<Square> {
+    a.min(b)..=a.max(b)
+}
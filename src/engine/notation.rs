@@ -0,0 +1,276 @@
+use crate::{
+    engine::{
+        board::{Board, Move, Square},
+        fen::{parse_square, square_name},
+    },
+    pieces::components::PieceType,
+};
+
+pub fn piece_letter(piece: PieceType) -> &'static str {
+    match piece {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+    }
+}
+
+// SAN for a move, board is the position *before* the move.
+// The move has to be legal, check / mate gets added at the end
+pub fn move_to_san(board: &Board, mv: Move) -> String {
+    let Some(moving) = board.piece_at(mv.from) else {
+        return move_to_uci(board, mv, false);
+    };
+
+    let mut san = if board.is_castling(mv) {
+        if mv.to > mv.from { "O-O" } else { "O-O-O" }.to_string()
+    } else {
+        let is_capture = board.captured_piece(mv).is_some();
+        let from_file = (b'a' + mv.from % 8) as char;
+
+        let disambiguation = if moving.kind == PieceType::Pawn {
+            if is_capture {
+                from_file.to_string()
+            } else {
+                String::new()
+            }
+        } else {
+            // other pieces of the same kind that could also go there
+            let others: Vec<Move> = board
+                .legal_moves()
+                .into_iter()
+                .filter(|m| {
+                    m.to == mv.to
+                        && m.from != mv.from
+                        && board.piece_at(m.from).map(|p| p.kind) == Some(moving.kind)
+                })
+                .collect();
+
+            if others.is_empty() {
+                String::new()
+            } else if others.iter().all(|m| m.from % 8 != mv.from % 8) {
+                from_file.to_string()
+            } else if others.iter().all(|m| m.from / 8 != mv.from / 8) {
+                (mv.from / 8 + 1).to_string()
+            } else {
+                square_name(mv.from)
+            }
+        };
+
+        let promotion = mv
+            .promotion
+            .map_or(String::new(), |p| format!("={}", piece_letter(p)));
+
+        format!(
+            "{}{}{}{}{}",
+            piece_letter(moving.kind),
+            disambiguation,
+            if is_capture { "x" } else { "" },
+            square_name(mv.to),
+            promotion
+        )
+    };
+
+    let after = board.make_move(mv);
+
+    if after.in_check() {
+        san.push(if after.legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+
+    san
+}
+
+// Long algebraic notation like UCI engines use it, e.g. "e2e4" or "e7e8q".
+// Castling is the king's two square move (e1g1), chess960 engines and positions
+// that can't be written that way get the king taking its own rook (e1h1)
+pub fn move_to_uci(board: &Board, mv: Move, chess960: bool) -> String {
+    let promotion = match mv.promotion {
+        Some(PieceType::Knight) => "n",
+        Some(PieceType::Bishop) => "b",
+        Some(PieceType::Rook) => "r",
+        Some(PieceType::Queen) => "q",
+        _ => "",
+    };
+
+    let to = if board.is_castling(mv) && !chess960 && !board.is_chess960() {
+        board.castling_targets(mv).0
+    } else {
+        mv.to
+    };
+
+    format!("{}{}{}", square_name(mv.from), square_name(to), promotion)
+}
+
+// Reads a typed move, either SAN ("Nf3", "exd5", "e8=Q", "O-O") or coordinates ("e2e4", "e7e8q").
+// Pawns reaching the last rank without a piece given become a queen, same as when clicking
+pub fn parse_move(board: &Board, text: &str) -> Result<Move, String> {
+    let text = text.trim();
+    // check, mate and annotations don't change which move it is
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?'))
+        .collect();
+
+    if cleaned.is_empty() {
+        return Err("Type a move like Nf3, e2e4 or O-O".to_string());
+    }
+
+    let legal = board.legal_moves();
+
+    let candidates: Vec<Move> = if let Some(king_side) = parse_castling(&cleaned) {
+        legal
+            .into_iter()
+            .filter(|m| board.is_castling(*m) && (m.to > m.from) == king_side)
+            .collect()
+    } else if let Some((from, to, promotion)) = parse_coordinates(&cleaned) {
+        board.find_move(from, to, promotion).into_iter().collect()
+    } else if let Some(san) = parse_san(&cleaned) {
+        legal
+            .into_iter()
+            .filter(|m| san.matches(board, *m))
+            .collect()
+    } else {
+        return Err(format!("Couldn't read \"{}\"", text));
+    };
+
+    match candidates.as_slice() {
+        [] => Err(format!("{} isn't a legal move here", text)),
+        [mv] => Ok(*mv),
+        _ => {
+            let options: Vec<String> = candidates.iter().map(|m| move_to_san(board, *m)).collect();
+
+            Err(format!(
+                "{} is ambiguous, it could be {}",
+                text,
+                options.join(" or ")
+            ))
+        }
+    }
+}
+
+// Some(true) for king side, Some(false) for queen side
+fn parse_castling(text: &str) -> Option<bool> {
+    match text.replace('0', "O").as_str() {
+        "O-O" | "OO" => Some(true),
+        "O-O-O" | "OOO" => Some(false),
+        _ => None,
+    }
+}
+
+// "e2e4", "e2-e4", "e7e8q"
+fn parse_coordinates(text: &str) -> Option<(Square, Square, Option<PieceType>)> {
+    let text = text.replace('-', "");
+
+    if !(4..=5).contains(&text.len()) || !text.is_ascii() {
+        return None;
+    }
+
+    let from = parse_square(&text[0..2])?;
+    let to = parse_square(&text[2..4])?;
+    let promotion = match text[4..].chars().next() {
+        Some(letter) => Some(piece_from_letter(letter.to_ascii_uppercase())?),
+        None => None,
+    };
+
+    Some((from, to, promotion))
+}
+
+// A move the way it's written in SAN, from_file / from_rank are only there when it was disambiguated
+struct SanMove {
+    kind: PieceType,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: Square,
+    promotion: Option<PieceType>,
+}
+
+impl SanMove {
+    fn matches(&self, board: &Board, mv: Move) -> bool {
+        board.piece_at(mv.from).map(|p| p.kind) == Some(self.kind)
+            && mv.to == self.to
+            && self.from_file.is_none_or(|file| mv.from % 8 == file)
+            && self.from_rank.is_none_or(|rank| mv.from / 8 == rank)
+            && promotion_matches(mv, self.promotion)
+    }
+}
+
+fn parse_san(text: &str) -> Option<SanMove> {
+    // captures and the "=" before a promotion aren't needed to find the move
+    let mut chars: Vec<char> = text
+        .chars()
+        .filter(|c| !matches!(c, 'x' | ':' | '='))
+        .collect();
+
+    let kind = match chars.first() {
+        Some('P') => {
+            chars.remove(0);
+            PieceType::Pawn
+        }
+        Some(&letter) => match piece_from_letter(letter) {
+            Some(kind) => {
+                chars.remove(0);
+                kind
+            }
+            None => PieceType::Pawn,
+        },
+        None => return None,
+    };
+
+    // the target square always ends with the rank, so a letter at the end is a promotion
+    let promotion = match chars.last() {
+        Some(letter) if letter.is_ascii_alphabetic() => {
+            let piece = piece_from_letter(letter.to_ascii_uppercase())?;
+            chars.pop();
+            Some(piece)
+        }
+        _ => None,
+    };
+
+    if chars.len() < 2 || (promotion.is_some() && kind != PieceType::Pawn) {
+        return None;
+    }
+
+    let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let to = parse_square(&target)?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+
+    for c in chars {
+        match c {
+            'a'..='h' if from_file.is_none() => from_file = Some(c as u8 - b'a'),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    Some(SanMove {
+        kind,
+        from_file,
+        from_rank,
+        to,
+        promotion,
+    })
+}
+
+fn piece_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+// No promotion given means a queen
+fn promotion_matches(mv: Move, promotion: Option<PieceType>) -> bool {
+    mv.promotion.is_none() || mv.promotion == Some(promotion.unwrap_or(PieceType::Queen))
+}
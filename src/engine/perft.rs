@@ -0,0 +1,141 @@
+use crate::engine::board::{Board, Move};
+
+// Positions with known move counts, the usual ones from the chess programming wiki.
+// Between them they have castling through and out of check, en passant that would leave
+// the king in check, promotions with and without captures, and lots of pinned pieces.
+// The last two are chess960 ones with the rook files in the castling field.
+// counts[0] is depth 1
+pub struct PerftPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub counts: &'static [u64],
+}
+
+pub const SUITE: [PerftPosition; 9] = [
+    PerftPosition {
+        name: "Start position",
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        counts: &[20, 400, 8_902, 197_281, 4_865_609],
+    },
+    PerftPosition {
+        name: "Kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        counts: &[48, 2_039, 97_862, 4_085_603],
+    },
+    PerftPosition {
+        name: "Position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        counts: &[14, 191, 2_812, 43_238, 674_624],
+    },
+    PerftPosition {
+        name: "Position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        counts: &[6, 264, 9_467, 422_333],
+    },
+    PerftPosition {
+        name: "Position 4 mirrored",
+        fen: "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        counts: &[6, 264, 9_467, 422_333],
+    },
+    PerftPosition {
+        name: "Position 5",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        counts: &[44, 1_486, 62_379, 2_103_487],
+    },
+    PerftPosition {
+        name: "Position 6",
+        fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        counts: &[46, 2_079, 89_890, 3_894_594],
+    },
+    PerftPosition {
+        name: "Chess960 1",
+        fen: "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        counts: &[21, 528, 12_189, 326_672],
+    },
+    PerftPosition {
+        name: "Chess960 2",
+        fen: "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        counts: &[21, 807, 18_002, 667_366],
+    },
+];
+
+// Every legal line of moves this deep, counted. When the number matches the known one
+// the move generator is most likely right, when it doesn't divide shows where it goes wrong
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = board.legal_moves();
+
+    // the last level only needs the number of moves, not the positions after them
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .into_iter()
+        .map(|mv| perft(&board.make_move(mv), depth - 1))
+        .sum()
+}
+
+// perft split up by the first move, to compare against another engine's numbers.
+// Depth 0 doesn't play a first move, so there's nothing to split up
+pub fn divide(board: &Board, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    board
+        .legal_moves()
+        .into_iter()
+        .map(|mv| (mv, perft(&board.make_move(mv), depth - 1)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // depth 4 takes a while in debug builds, the rest is left to "perft --suite"
+    const TEST_DEPTH: u32 = 3;
+
+    #[test]
+    fn suite_counts() {
+        for position in &SUITE {
+            let board = Board::from_fen(position.fen).unwrap();
+
+            for (depth, &expected) in (1..=TEST_DEPTH).zip(position.counts) {
+                assert_eq!(
+                    perft(&board, depth),
+                    expected,
+                    "{} depth {}",
+                    position.name,
+                    depth
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        for position in &SUITE {
+            let board = Board::from_fen(position.fen).unwrap();
+            let total: u64 = divide(&board, TEST_DEPTH)
+                .iter()
+                .map(|(_, nodes)| nodes)
+                .sum();
+
+            assert_eq!(total, perft(&board, TEST_DEPTH), "{}", position.name);
+        }
+    }
+
+    #[test]
+    fn depth_zero_is_the_position_itself() {
+        let board = Board::start();
+
+        assert_eq!(perft(&board, 0), 1);
+        assert!(divide(&board, 0).is_empty());
+        assert_eq!(perft(&board, 1), divide(&board, 1).len() as u64);
+    }
+}
@@ -0,0 +1,575 @@
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    engine::{
+        board::{Board, Move},
+        eval::{evaluate, piece_value},
+        transposition::{Bound, TableEntry, TranspositionTable},
+    },
+    pieces::components::{PieceColor, PieceType},
+};
+
+// Bigger than any evaluation, mates closer to the root score higher
+pub const MATE_SCORE: i32 = 100_000;
+
+// Anything this close to MATE_SCORE is a mate that was found, not an evaluation
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+
+// Deepest the search goes, checks and captures at the end of a line included
+const MAX_PLY: usize = 64;
+
+// Transposition table size, for the computer player, the analysis and UCI
+pub const TABLE_MEGABYTES: usize = 16;
+
+// How often (in nodes) the clock gets looked at
+const TIME_CHECK_NODES: u64 = 1024;
+
+// Never less than this for a move, even with almost nothing left on the clock
+const MIN_THINK_TIME: Duration = Duration::from_millis(50);
+
+// Move ordering, the higher the earlier a move is tried
+const HASH_MOVE_ORDER: i32 = 1_000_000;
+const CAPTURE_ORDER: i32 = 100_000;
+const KILLER_ORDER: i32 = 90_000;
+const HISTORY_MAX: i32 = 80_000;
+
+// A score the way people read it, always from white's side
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    // mate in this many moves, negative when black is the one mating
+    Mate(i32),
+    // nothing left to search, this side is mated
+    Checkmated(PieceColor),
+}
+
+impl Score {
+    // Search scores are for the side to move
+    pub fn from_search(score: i32, turn: PieceColor) -> Score {
+        let sign = match turn {
+            PieceColor::White => 1,
+            PieceColor::Black => -1,
+        };
+
+        if score.abs() >= MATE_THRESHOLD {
+            let plies = MATE_SCORE - score.abs();
+            Score::Mate(sign * score.signum() * (plies + 1) / 2)
+        } else {
+            Score::Centipawns(sign * score)
+        }
+    }
+
+    // "+1.3", "-0.4", "M3" (white mates in 3), "-M2" or "#"
+    pub fn text(&self) -> String {
+        match self {
+            Score::Centipawns(cp) => format!("{:+.1}", *cp as f32 / 100.0),
+            Score::Mate(moves) if *moves < 0 => format!("-M{}", -moves),
+            Score::Mate(moves) => format!("M{}", moves),
+            Score::Checkmated(_) => "#".to_string(),
+        }
+    }
+
+    // How much of the bar is white, between 0 and 1.
+    // Same curve lichess uses to turn centipawns into winning chances
+    pub fn white_share(&self) -> f32 {
+        match self {
+            Score::Centipawns(cp) => {
+                let winning_chances = 2.0 / (1.0 + (-0.003_682_08 * *cp as f32).exp()) - 1.0;
+                0.5 + 0.5 * winning_chances
+            }
+            Score::Mate(moves) if *moves < 0 => 0.0,
+            Score::Mate(_) => 1.0,
+            Score::Checkmated(PieceColor::White) => 0.0,
+            Score::Checkmated(PieceColor::Black) => 1.0,
+        }
+    }
+}
+
+// How far and how long a search may go. The deepest search that finished
+// in time is the one that gets played
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits {
+    pub depth: u8,
+    pub time: Option<Duration>,
+    // how many threads search at the same time
+    pub threads: usize,
+}
+
+// What the search found at the deepest depth it finished
+#[derive(Clone, Copy, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: i32,
+    pub depth: u8,
+    // all threads together
+    pub nodes: u64,
+}
+
+// How many threads the search can use on this machine
+pub fn max_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// About a thirtieth of what's left plus most of the increment, but never more than half of it.
+// The minimum comes first so it can't take more than half of an almost empty clock either
+pub fn time_budget(remaining: Duration, increment: Duration) -> Duration {
+    let budget = remaining / 30 + increment.mul_f32(0.8);
+
+    budget.max(MIN_THINK_TIME).min(remaining / 2)
+}
+
+// Fixed depth without a time limit on one thread, what the evaluation bar and hints use
+pub fn best_move(board: &Board, depth: u8, table: &TranspositionTable) -> Option<(Move, i32)> {
+    search(
+        board,
+        SearchLimits {
+            depth,
+            time: None,
+            threads: 1,
+        },
+        table,
+    )
+    .map(|result| (result.best_move, result.score))
+}
+
+pub fn search(
+    board: &Board,
+    limits: SearchLimits,
+    table: &TranspositionTable,
+) -> Option<SearchResult> {
+    search_with(board, &[], limits, table, &AtomicBool::new(false), |_| {})
+}
+
+// Lazy SMP: every thread runs its own search of the same position, and they all share
+// one transposition table. A thread that finds a position another one already searched
+// just takes the result, so together they get deeper than one would on its own.
+// Only the main thread's result counts, the others stop when it's done.
+// played has the hashes of the game's positions before this one, going back to one of them is a draw.
+// report gets called after every depth the main thread finishes, setting stop
+// (from another thread) ends the search early
+pub fn search_with(
+    board: &Board,
+    played: &[u64],
+    limits: SearchLimits,
+    table: &TranspositionTable,
+    stop: &AtomicBool,
+    report: impl FnMut(&SearchResult),
+) -> Option<SearchResult> {
+    let nodes = AtomicU64::new(0);
+    let finished = AtomicBool::new(false);
+    let deadline = limits.time.map(|time| Instant::now() + time);
+
+    thread::scope(|scope| {
+        for helper in 1..limits.threads.max(1) {
+            let mut searcher = Searcher::new(table, &finished, &nodes, None, played);
+
+            scope.spawn(move || {
+                searcher.can_stop = true;
+
+                // every other helper starts one depth further, so they don't all search the same thing
+                let mut last_best = None;
+
+                for depth in 1 + (helper % 2) as u8..=limits.depth.max(1) {
+                    let result = searcher.root(board, depth, last_best);
+
+                    if searcher.stopped {
+                        break;
+                    }
+
+                    last_best = result.map(|(mv, _)| mv);
+                }
+            });
+        }
+
+        let mut searcher = Searcher::new(table, stop, &nodes, deadline, played);
+        let result = searcher.iterative_deepening(board, limits.depth, report);
+
+        // tells the helpers to stop, stop itself is left alone since it belongs to the caller
+        finished.store(true, Ordering::Relaxed);
+
+        result
+    })
+}
+
+struct Searcher<'a> {
+    table: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    // shared by all threads, each one adds its nodes every so often
+    total_nodes: &'a AtomicU64,
+    deadline: Option<Instant>,
+    can_stop: bool,
+    stopped: bool,
+    nodes: u64,
+    // quiet moves that caused a cutoff at the same ply somewhere else in the tree
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    // how often a quiet move from one square to another was good enough for a cutoff
+    history: Box<[[i32; 64]; 64]>,
+    // hashes of the positions on the way to this one (the game's first), for repetitions
+    path: Vec<u64>,
+    // a draw by repetition or the fifty move rule was found below the current node.
+    // Those depend on how the position was reached, so the score doesn't go into the table
+    drawn_by_history: bool,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        table: &'a TranspositionTable,
+        stop: &'a AtomicBool,
+        total_nodes: &'a AtomicU64,
+        deadline: Option<Instant>,
+        played: &[u64],
+    ) -> Searcher<'a> {
+        Searcher {
+            table,
+            stop,
+            total_nodes,
+            deadline,
+            can_stop: false,
+            stopped: false,
+            nodes: 0,
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[0; 64]; 64]),
+            path: played.to_vec(),
+            drawn_by_history: false,
+        }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if self.nodes.is_multiple_of(TIME_CHECK_NODES) {
+            self.total_nodes
+                .fetch_add(TIME_CHECK_NODES, Ordering::Relaxed);
+
+            let time_is_up = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+
+            if self.can_stop && (time_is_up || self.stop.load(Ordering::Relaxed)) {
+                self.stopped = true;
+            }
+        }
+
+        self.stopped
+    }
+
+    // Depth 1, 2, 3... until the depth or the time runs out. Each pass fills the
+    // transposition table and the move ordering for the next one, so the deeper
+    // passes cost less than they would on their own
+    fn iterative_deepening(
+        &mut self,
+        board: &Board,
+        max_depth: u8,
+        mut report: impl FnMut(&SearchResult),
+    ) -> Option<SearchResult> {
+        let mut best: Option<SearchResult> = None;
+
+        for depth in 1..=max_depth.max(1) {
+            let result = self.root(board, depth, best.map(|result| result.best_move));
+
+            // a depth that didn't finish still counts when one of its moves was better,
+            // the last best move was searched first
+            if let Some((best_move, score)) = result {
+                best = Some(SearchResult {
+                    best_move,
+                    score,
+                    depth: best.map_or(depth, |best| best.depth),
+                    nodes: self.total_nodes.load(Ordering::Relaxed) + self.nodes % TIME_CHECK_NODES,
+                });
+            }
+
+            if self.stopped {
+                break;
+            }
+
+            let Some(finished) = best.as_mut() else {
+                break;
+            };
+
+            finished.depth = depth;
+            report(finished);
+
+            // a mate that was found doesn't get any better by searching deeper
+            if finished.score.abs() >= MATE_THRESHOLD {
+                break;
+            }
+
+            // depth 1 always finishes so there's a move, after that the clock counts
+            self.can_stop = true;
+        }
+
+        best
+    }
+
+    // Best move of the moves that were searched all the way. When the time runs out
+    // in the middle, the ones that finished (the last best move goes first) still count
+    fn root(&mut self, board: &Board, depth: u8, last_best: Option<Move>) -> Option<(Move, i32)> {
+        let mut moves = board.legal_moves();
+
+        self.order_moves(board, &mut moves, last_best, 0);
+
+        let mut best = None;
+        let mut alpha = -MATE_SCORE - 1;
+
+        self.path.push(board.hash());
+
+        for mv in moves {
+            let score =
+                -self.alpha_beta(&board.make_move(mv), depth - 1, 1, -MATE_SCORE - 1, -alpha);
+
+            if self.stopped {
+                break;
+            }
+
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((mv, score));
+            }
+        }
+
+        self.path.pop();
+
+        best
+    }
+
+    fn alpha_beta(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let hash = board.hash();
+
+        // fifty move rule, or the same position again on the way here
+        if board.halfmove_clock >= 100 || self.path.contains(&hash) {
+            self.drawn_by_history = true;
+            return 0;
+        }
+
+        if ply >= MAX_PLY {
+            return evaluate(board);
+        }
+
+        let in_check = board.in_check();
+
+        // a check gets looked at one move further, so it doesn't get cut off right before the mate
+        let depth = if in_check { depth + 1 } else { depth };
+
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        let entry = self.table.probe(hash);
+
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        let mut moves = board.legal_moves();
+
+        if moves.is_empty() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        self.order_moves(
+            board,
+            &mut moves,
+            entry.and_then(|entry| entry.best_move),
+            ply,
+        );
+
+        let original_alpha = alpha;
+        let mut best_score = -MATE_SCORE - 1;
+        let mut best_move = None;
+
+        // only what's found under this node decides whether it can be stored
+        let drawn_above = std::mem::replace(&mut self.drawn_by_history, false);
+
+        self.path.push(hash);
+
+        for mv in moves {
+            let score = -self.alpha_beta(&board.make_move(mv), depth - 1, ply + 1, -beta, -alpha);
+
+            if self.stopped {
+                self.path.pop();
+                self.drawn_by_history |= drawn_above;
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+
+            alpha = alpha.max(score);
+
+            if alpha >= beta {
+                if board.captured_piece(mv).is_none() {
+                    self.remember_quiet_move(mv, depth, ply);
+                }
+
+                break;
+            }
+        }
+
+        self.path.pop();
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+
+        if !self.drawn_by_history {
+            self.table.store(
+                hash,
+                TableEntry {
+                    best_move,
+                    score: score_to_table(best_score, ply),
+                    depth,
+                    bound,
+                },
+            );
+        }
+
+        // the score goes up to the nodes above, so they can't be stored either
+        self.drawn_by_history |= drawn_above;
+
+        best_score
+    }
+
+    // Only captures and queen promotions from here on, until the position is quiet.
+    // Stops the search from ending a line right in the middle of an exchange
+    fn quiescence(&mut self, board: &Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        if self.out_of_time() {
+            return 0;
+        }
+
+        // the side to move doesn't have to capture, standing still is worth this much
+        let stand_pat = evaluate(board);
+
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                board.captured_piece(*mv).is_some() || mv.promotion == Some(PieceType::Queen)
+            })
+            .collect();
+
+        self.order_moves(board, &mut moves, None, ply);
+
+        let mut best_score = stand_pat;
+
+        for mv in moves {
+            let score = -self.quiescence(&board.make_move(mv), ply + 1, -beta, -alpha);
+
+            if self.stopped {
+                return 0;
+            }
+
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    // Hash move first, then captures (most valuable victim, least valuable attacker),
+    // then the killer moves and the rest by their history
+    fn order_moves(&self, board: &Board, moves: &mut [Move], hash_move: Option<Move>, ply: usize) {
+        let killers = self.killers.get(ply).copied().unwrap_or_default();
+
+        moves.sort_by_cached_key(|&mv| {
+            let order = if Some(mv) == hash_move {
+                HASH_MOVE_ORDER
+            } else if let Some(victim) = board.captured_piece(mv) {
+                let attacker = board.piece_at(mv.from).map_or(0, |p| piece_value(p.kind));
+                CAPTURE_ORDER + piece_value(victim.kind) * 10 - attacker / 10
+            } else if mv.promotion == Some(PieceType::Queen) {
+                CAPTURE_ORDER
+            } else if killers[0] == Some(mv) {
+                KILLER_ORDER + 1
+            } else if killers[1] == Some(mv) {
+                KILLER_ORDER
+            } else {
+                self.history[mv.from as usize][mv.to as usize]
+            };
+
+            Reverse(order)
+        });
+    }
+
+    fn remember_quiet_move(&mut self, mv: Move, depth: u8, ply: usize) {
+        if let Some(killers) = self
+            .killers
+            .get_mut(ply)
+            .filter(|killers| killers[0] != Some(mv))
+        {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let history = &mut self.history[mv.from as usize][mv.to as usize];
+        *history = (*history + depth as i32 * depth as i32).min(HISTORY_MAX);
+    }
+}
+
+// Mate scores count from the root, but the same position can come up at another ply.
+// In the table they count from the position itself
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{engine::board::Move, pieces::components::PieceType};
+
+// What the stored score says about the real one. The search only gets an exact score
+// when it didn't cut off, otherwise it just knows the score is at least or at most this
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TableEntry {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+// Both halves are atomics so several searches can share one table without locking.
+// check is the key XORed with the data, an entry where one thread's key got mixed
+// up with another thread's data doesn't match anymore and just gets ignored
+#[derive(Default)]
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+// Positions that were already searched, by Zobrist hash. Fixed size,
+// newer and deeper results push older ones out of their slot.
+// It's kept from one search to the next, so the next move starts with what the last one found
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    // Size in megabytes, rounded down to a power of two slots
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let count = megabytes.max(1) * 1024 * 1024 / size_of::<Slot>();
+        let count = 1 << count.ilog2();
+
+        TranspositionTable {
+            slots: (0..count).map(|_| Slot::default()).collect(),
+        }
+    }
+
+    // For a new game, the old positions won't come up again
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, hash: u64) -> &Slot {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = self.slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+
+        if data == 0 || slot.check.load(Ordering::Relaxed) ^ data != hash {
+            return None;
+        }
+
+        Some(unpack(data))
+    }
+
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        let slot = self.slot(hash);
+
+        // a shallow result for the same position doesn't throw away a deeper one
+        if self
+            .probe(hash)
+            .is_some_and(|old| old.depth > entry.depth && entry.bound != Bound::Exact)
+        {
+            return;
+        }
+
+        let data = pack(entry);
+
+        slot.check.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+// Everything fits in one u64: the move in the low 16 bits (bit 15 says there is one),
+// then the depth, the bound and the score in the top 32 bits
+fn pack(entry: TableEntry) -> u64 {
+    let mv = entry.best_move.map_or(0, |mv| {
+        let promotion = match mv.promotion {
+            None => 0,
+            Some(PieceType::Knight) => 1,
+            Some(PieceType::Bishop) => 2,
+            Some(PieceType::Rook) => 3,
+            Some(_) => 4,
+        };
+
+        (1 << 15) | (promotion << 12) | ((mv.to as u64) << 6) | mv.from as u64
+    });
+
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    ((entry.score as u32 as u64) << 32) | (bound << 24) | ((entry.depth as u64) << 16) | mv
+}
+
+fn unpack(data: u64) -> TableEntry {
+    let best_move = (data & (1 << 15) != 0).then_some(Move {
+        from: (data & 63) as u8,
+        to: ((data >> 6) & 63) as u8,
+        promotion: match (data >> 12) & 7 {
+            1 => Some(PieceType::Knight),
+            2 => Some(PieceType::Bishop),
+            3 => Some(PieceType::Rook),
+            4 => Some(PieceType::Queen),
+            _ => None,
+        },
+    });
+
+    let bound = match (data >> 24) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+
+    TableEntry {
+        best_move,
+        score: (data >> 32) as u32 as i32,
+        depth: (data >> 16) as u8,
+        bound,
+    }
+}
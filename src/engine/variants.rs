@@ -0,0 +1,126 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::{
+    engine::board::{Board, Piece},
+    pieces::components::{PieceColor, PieceType},
+};
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    // Back rank gets shuffled, the king still castles with the rook on either side of it
+    Chess960,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 2] = [Variant::Standard, Variant::Chess960];
+
+    // What the setup menu shows
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::Chess960 => "Chess960",
+        }
+    }
+
+    pub fn starting_board(&self) -> Board {
+        match self {
+            Variant::Standard => Board::start(),
+            Variant::Chess960 => {
+                // doesn't need to be a good random number, just a different one every game
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.subsec_nanos())
+                    .unwrap_or(0);
+
+                chess960_board((seed % 960) as u16)
+            }
+        }
+    }
+}
+
+// Chess960 start position by its number (0 - 959, 518 is the normal setup)
+// https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+pub fn chess960_board(number: u16) -> Board {
+    let mut back_rank: [Option<PieceType>; 8] = [None; 8];
+    let mut n = number as usize % 960;
+
+    // bishops go on opposite colors, b/d/f/h are the light squares
+    back_rank[n % 4 * 2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    back_rank[n % 4 * 2] = Some(PieceType::Bishop);
+    n /= 4;
+
+    let mut place = |kind: PieceType, nth_empty: usize| {
+        let file = (0..8)
+            .filter(|f| back_rank[*f].is_none())
+            .nth(nth_empty)
+            .expect("there's always an empty file left");
+        back_rank[file] = Some(kind);
+    };
+
+    place(PieceType::Queen, n % 6);
+    n /= 6;
+
+    let knights = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ][n];
+
+    // the second knight is counted after the first one was placed
+    place(PieceType::Knight, knights.0);
+    place(PieceType::Knight, knights.1 - 1);
+
+    // king always ends up between the rooks
+    place(PieceType::Rook, 0);
+    place(PieceType::King, 0);
+    place(PieceType::Rook, 0);
+
+    let mut board = Board::empty();
+
+    // the first rook placed is the queen side one
+    let rook_files: Vec<u8> = (0..8)
+        .filter(|f| back_rank[*f as usize] == Some(PieceType::Rook))
+        .collect();
+
+    for color in [PieceColor::White, PieceColor::Black] {
+        for (king_side, file) in [(true, rook_files[1]), (false, rook_files[0])] {
+            board.castling.set(color, king_side, true);
+            board.castling.set_rook_file(color, king_side, file);
+        }
+    }
+
+    for (file, kind) in back_rank.iter().enumerate() {
+        let kind = kind.expect("every file got a piece");
+
+        board.squares[file] = Some(Piece {
+            kind,
+            color: PieceColor::White,
+        });
+        board.squares[file + 8] = Some(Piece {
+            kind: PieceType::Pawn,
+            color: PieceColor::White,
+        });
+        board.squares[file + 48] = Some(Piece {
+            kind: PieceType::Pawn,
+            color: PieceColor::Black,
+        });
+        board.squares[file + 56] = Some(Piece {
+            kind,
+            color: PieceColor::Black,
+        });
+    }
+
+    board
+}
@@ -0,0 +1,86 @@
+use crate::{
+    engine::board::Board,
+    pieces::components::{PieceColor, PieceType},
+};
+
+// Random numbers for every piece on every square, the side to move, the castling rights
+// and the en passant file. XORing the ones that are in a position together gives its hash.
+// Made at compile time with splitmix64 so they're the same every run
+const PIECE_KEYS: usize = 12 * 64;
+const TURN_KEY: usize = PIECE_KEYS;
+const CASTLING_KEYS: usize = TURN_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const KEY_COUNT: usize = EN_PASSANT_KEYS + 8;
+
+const KEYS: [u64; KEY_COUNT] = generate_keys();
+
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+
+        i += 1;
+    }
+
+    keys
+}
+
+fn piece_index(kind: PieceType, color: PieceColor) -> usize {
+    let kind = match kind {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+
+    match color {
+        PieceColor::White => kind,
+        PieceColor::Black => kind + 6,
+    }
+}
+
+impl Board {
+    // Zobrist hash of the position, the same position always gives the same number.
+    // The move counters aren't in it, they don't change what can be played
+    pub fn hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (square, piece) in self.pieces() {
+            hash ^= KEYS[piece_index(piece.kind, piece.color) * 64 + square as usize];
+        }
+
+        if self.turn == PieceColor::Black {
+            hash ^= KEYS[TURN_KEY];
+        }
+
+        for (i, allowed) in [
+            self.castling.white_king_side,
+            self.castling.white_queen_side,
+            self.castling.black_king_side,
+            self.castling.black_queen_side,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if allowed {
+                hash ^= KEYS[CASTLING_KEYS + i];
+            }
+        }
+
+        if let Some(square) = self.en_passant {
+            hash ^= KEYS[EN_PASSANT_KEYS + (square % 8) as usize];
+        }
+
+        hash
+    }
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+use std::sync::Arc;
+
+pub mod resources;
+mod review;
+pub mod systems;
+
+use resources::*;
+use review::*;
+use systems::*;
+
+use crate::{
+    engine::{search::TABLE_MEGABYTES, transposition::TranspositionTable},
+    game::MoveSet,
+    states::{AppState, board_active},
+};
+
+pub struct EvaluationPlugin;
+
+// The bar next to the board with how good the position is, the hint button's suggestions
+// and the review of finished games. All of it is worked out on the async compute pool
+// (built-in search or the external engine from the settings)
+impl Plugin for EvaluationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Evaluation>()
+            .register_type::<EngineAnswer>()
+            .insert_resource(Evaluation {
+                answer: None,
+                board: None,
+                error: None,
+            })
+            .insert_resource(EvaluationTask {
+                task: None,
+                board: None,
+            })
+            .register_type::<Hint>()
+            .insert_resource(EngineProcess::default())
+            .insert_resource(AnalysisTable(Arc::new(TranspositionTable::new(
+                TABLE_MEGABYTES,
+            ))))
+            .insert_resource(Hint {
+                board: None,
+                best_move: None,
+                level: HintLevel::Hidden,
+                searching: false,
+            })
+            .insert_resource(HintTask {
+                task: None,
+                board: None,
+            })
+            .add_event::<RequestHint>()
+            .register_type::<GameReview>()
+            .insert_resource(GameReview {
+                nodes: Vec::new(),
+                boards: Vec::new(),
+                scores: Vec::new(),
+                best_moves: Vec::new(),
+            })
+            .insert_resource(ReviewTask { task: None })
+            .add_systems(OnEnter(AppState::GameOver), start_review)
+            .add_systems(Update, (clear_review, run_review).chain())
+            .add_systems(
+                Update,
+                (start_evaluation, finish_evaluation)
+                    .chain()
+                    .after(MoveSet)
+                    .run_if(board_active.and(eval_bar_enabled)),
+            )
+            .add_systems(
+                Update,
+                (hint_key, request_hint, finish_hint, clear_hint, draw_hint)
+                    .chain()
+                    .after(MoveSet)
+                    .run_if(board_active),
+            )
+            .add_systems(
+                EguiContextPass,
+                (
+                    eval_bar_ui.run_if(board_active.and(eval_bar_enabled)),
+                    // the game over dialog is in the way until it's closed with Analyze
+                    review_ui.run_if(in_state(AppState::Analysis)),
+                ),
+            );
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{prelude::*, tasks::Task};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    engine::{
+        board::{Board, Move},
+        external::ExternalEngine,
+        search::Score,
+        transposition::TranspositionTable,
+    },
+    pieces::components::PieceColor,
+};
+
+// Room between the clock panel and the board for the bar, in logical pixels
+pub const EVAL_BAR_WIDTH: f32 = 28.0;
+
+// How deep the built-in search looks for the bar and how long an external engine gets
+pub const BUILT_IN_DEPTH: u8 = 3;
+pub const ENGINE_MOVE_TIME: u64 = 1000;
+
+// Every position of a finished game gets this much for the review
+pub const REVIEW_DEPTH: u8 = 3;
+pub const REVIEW_MOVE_TIME: u64 = 300;
+
+// Hints are a shorter search
+pub const HINT_DEPTH: u8 = 3;
+pub const HINT_MOVE_TIME: u64 = 500;
+
+// Purple, so it doesn't look like one of the arrows that were drawn by hand
+pub const HINT_COLOR: Color = Color::srgba(0.6, 0.2, 0.8, 0.8);
+
+// What an engine thinks about a position
+#[derive(Reflect, Debug, Clone)]
+pub struct EngineAnswer {
+    pub best_move: Option<Move>,
+    pub score: Option<Score>,
+    // "Built-in" or the name the external engine gave itself
+    pub engine: String,
+}
+
+// The evaluation that's shown next to the board and the position it's for.
+// error is what went wrong with the external engine, if it did
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct Evaluation {
+    pub answer: Option<EngineAnswer>,
+    pub board: Option<Board>,
+    pub error: Option<String>,
+}
+
+// The search for the bar that's running right now. Can't be reflected, same as AiTask
+#[derive(Resource)]
+pub struct EvaluationTask {
+    pub task: Option<Task<Result<EngineAnswer, String>>>,
+    pub board: Option<Board>,
+}
+
+// The external engine keeps running between searches instead of being started for every move.
+// The tasks lock it while they use it, so searches for it happen one after another
+#[derive(Resource, Clone, Default)]
+pub struct EngineProcess(pub Arc<Mutex<Option<ExternalEngine>>>);
+
+// Same for the built-in search, the bar, the hints and the review all fill the same table
+#[derive(Resource, Clone)]
+pub struct AnalysisTable(pub Arc<TranspositionTable>);
+
+// How much of the hint is shown
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum HintLevel {
+    #[default]
+    Hidden,
+    // only the piece that should move gets circled
+    Piece,
+    // the arrow for the whole move
+    Move,
+}
+
+// The suggested move for the position on the board. It goes away once the board shows anything else
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Hint {
+    pub board: Option<Board>,
+    pub best_move: Option<Move>,
+    pub level: HintLevel,
+    // the search for it is still running
+    pub searching: bool,
+}
+
+#[derive(Resource)]
+pub struct HintTask {
+    pub task: Option<Task<Result<EngineAnswer, String>>>,
+    pub board: Option<Board>,
+}
+
+// Hint button or H, every time shows a bit more of the hint
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct RequestHint;
+
+// How bad a move was, going by how much it dropped the winning chances of whoever played it.
+// Same limits as lichess (10, 20 and 30 percent)
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MoveQuality {
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    pub fn from_loss(loss: f32) -> MoveQuality {
+        if loss >= 30.0 {
+            MoveQuality::Blunder
+        } else if loss >= 20.0 {
+            MoveQuality::Mistake
+        } else if loss >= 10.0 {
+            MoveQuality::Inaccuracy
+        } else {
+            MoveQuality::Good
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MoveQuality::Good => "",
+            MoveQuality::Inaccuracy => "?!",
+            MoveQuality::Mistake => "?",
+            MoveQuality::Blunder => "??",
+        }
+    }
+}
+
+// Analysis of a finished game, one position after the other.
+// nodes / boards are the game's line when it ended, scores and best_moves get filled in
+// as the searches come back (None when one failed)
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct GameReview {
+    pub nodes: Vec<usize>,
+    pub boards: Vec<Board>,
+    pub scores: Vec<Option<Score>>,
+    pub best_moves: Vec<Option<Move>>,
+}
+
+impl GameReview {
+    pub fn is_done(&self) -> bool {
+        self.scores.len() >= self.boards.len()
+    }
+
+    // White's winning chances in percent in position i
+    fn white_chances(&self, i: usize) -> Option<f32> {
+        self.scores
+            .get(i)
+            .copied()
+            .flatten()
+            .map(|score| score.white_share() * 100.0)
+    }
+
+    // How much move i (boards[i] to boards[i + 1]) lowered the winning chances of the side that played it
+    pub fn loss(&self, i: usize) -> Option<f32> {
+        let before = self.white_chances(i)?;
+        let after = self.white_chances(i + 1)?;
+
+        let loss = match self.boards[i].turn {
+            PieceColor::White => before - after,
+            PieceColor::Black => after - before,
+        };
+
+        Some(loss.max(0.0))
+    }
+
+    pub fn quality(&self, i: usize) -> Option<MoveQuality> {
+        self.loss(i).map(MoveQuality::from_loss)
+    }
+
+    // Indices of the moves that one side played
+    pub fn moves_by(&self, color: PieceColor) -> impl Iterator<Item = usize> + '_ {
+        (0..self.boards.len().saturating_sub(1)).filter(move |&i| self.boards[i].turn == color)
+    }
+
+    // Average of lichess' accuracy per move, 100 when nothing was lost
+    pub fn accuracy(&self, color: PieceColor) -> Option<f32> {
+        let accuracies: Vec<f32> = self
+            .moves_by(color)
+            .filter_map(|i| self.loss(i))
+            .map(|loss| (103.1668 * (-0.04354 * loss).exp() - 3.1669).clamp(0.0, 100.0))
+            .collect();
+
+        if accuracies.is_empty() {
+            None
+        } else {
+            Some(accuracies.iter().sum::<f32>() / accuracies.len() as f32)
+        }
+    }
+
+    pub fn count(&self, color: PieceColor, quality: MoveQuality) -> usize {
+        self.moves_by(color)
+            .filter(|&i| self.quality(i) == Some(quality))
+            .count()
+    }
+}
+
+// The search for the next position of the review
+#[derive(Resource)]
+pub struct ReviewTask {
+    pub task: Option<Task<Result<EngineAnswer, String>>>,
+}
@@ -0,0 +1,319 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, futures::check_ready},
+};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    engine::notation::move_to_san,
+    evaluation::{resources::*, systems::analyse},
+    game::resources::{MoveHistory, NewGame},
+    pieces::components::PieceColor,
+    settings::resources::Settings,
+};
+
+// The game that just ended gets analysed, every position of the line it was played in
+pub fn start_review(
+    history: Res<MoveHistory>,
+    mut review: ResMut<GameReview>,
+    mut task: ResMut<ReviewTask>,
+) {
+    *review = GameReview {
+        nodes: history.line.clone(),
+        boards: history.positions.clone(),
+        scores: Vec::new(),
+        best_moves: Vec::new(),
+    };
+    task.task = None;
+
+    println!("Reviewing {} positions", review.boards.len());
+}
+
+// A new game throws the review of the last one away
+pub fn clear_review(
+    mut events: EventReader<NewGame>,
+    mut review: ResMut<GameReview>,
+    mut task: ResMut<ReviewTask>,
+) {
+    if events.read().count() == 0 || review.boards.is_empty() {
+        return;
+    }
+
+    *review = GameReview::default();
+    task.task = None;
+}
+
+// One search at a time, the next one starts when the last one is back
+pub fn run_review(
+    settings: Res<Settings>,
+    process: Res<EngineProcess>,
+    table: Res<AnalysisTable>,
+    mut review: ResMut<GameReview>,
+    mut task: ResMut<ReviewTask>,
+) {
+    if let Some(running) = task.task.as_mut() {
+        let Some(result) = check_ready(running) else {
+            return;
+        };
+
+        task.task = None;
+
+        match result {
+            Ok(answer) => {
+                review.scores.push(answer.score);
+                review.best_moves.push(answer.best_move);
+            }
+            Err(error) => {
+                println!("Couldn't review a position: {}", error);
+                review.scores.push(None);
+                review.best_moves.push(None);
+            }
+        }
+    }
+
+    if review.is_done() {
+        return;
+    }
+
+    let board = review.boards[review.scores.len()];
+    let engine_path = settings.engine_path.clone();
+    let process = process.clone();
+    let table = table.clone();
+
+    task.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        analyse(
+            board,
+            &engine_path,
+            &process,
+            &table,
+            REVIEW_DEPTH,
+            REVIEW_MOVE_TIME,
+        )
+    }));
+}
+
+fn quality_color(quality: MoveQuality) -> egui::Color32 {
+    match quality {
+        MoveQuality::Good => egui::Color32::GRAY,
+        MoveQuality::Inaccuracy => egui::Color32::from_rgb(230, 200, 60),
+        MoveQuality::Mistake => egui::Color32::from_rgb(230, 140, 40),
+        MoveQuality::Blunder => egui::Color32::from_rgb(220, 50, 40),
+    }
+}
+
+// "12. Nf3" or "12... Nf3" for the move that led to position i of the review
+fn move_name(history: &MoveHistory, review: &GameReview, i: usize) -> String {
+    let record = review
+        .nodes
+        .get(i)
+        .and_then(|&node| history.nodes.get(node))
+        .and_then(|node| node.record.as_ref());
+
+    match (record, i.checked_sub(1).map(|before| review.boards[before])) {
+        (Some(record), Some(before)) => {
+            let dots = match before.turn {
+                PieceColor::White => ".",
+                PieceColor::Black => "...",
+            };
+
+            format!("{}{} {}", before.fullmove_number, dots, record.san)
+        }
+        _ => "Start".to_string(),
+    }
+}
+
+// Accuracy and mistakes per player, the evaluation over the whole game as a graph
+// and a list of the bad moves. Clicking a point or a move goes to that position
+pub fn review_ui(
+    mut contexts: EguiContexts,
+    review: Res<GameReview>,
+    mut history: ResMut<MoveHistory>,
+) {
+    if review.boards.is_empty() {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+    let mut clicked = None;
+
+    egui::Window::new("Game review")
+        .default_width(380.0)
+        .default_pos(egui::pos2(200.0, 60.0))
+        .show(ctx, |ui| {
+            if !review.is_done() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!(
+                        "Analysing position {} of {}",
+                        review.scores.len() + 1,
+                        review.boards.len()
+                    ));
+                });
+            }
+
+            egui::Grid::new("review_summary")
+                .num_columns(3)
+                .spacing([30.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.strong("White");
+                    ui.strong("Black");
+                    ui.end_row();
+
+                    ui.label("Accuracy");
+                    for color in [PieceColor::White, PieceColor::Black] {
+                        ui.label(
+                            review
+                                .accuracy(color)
+                                .map_or("-".to_string(), |a| format!("{:.0}%", a)),
+                        );
+                    }
+                    ui.end_row();
+
+                    for (quality, name) in [
+                        (MoveQuality::Inaccuracy, "Inaccuracies"),
+                        (MoveQuality::Mistake, "Mistakes"),
+                        (MoveQuality::Blunder, "Blunders"),
+                    ] {
+                        ui.colored_label(quality_color(quality), name);
+                        for color in [PieceColor::White, PieceColor::Black] {
+                            ui.label(review.count(color, quality).to_string());
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            ui.add_space(6.0);
+
+            if let Some(index) = evaluation_graph(ui, &review, &history) {
+                clicked = Some(index);
+            }
+
+            ui.add_space(6.0);
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for i in 0..review.scores.len().saturating_sub(1) {
+                        let Some(quality) = review.quality(i).filter(|q| *q != MoveQuality::Good)
+                        else {
+                            continue;
+                        };
+
+                        let best = review.best_moves[i]
+                            .map(|mv| move_to_san(&review.boards[i], mv))
+                            .unwrap_or_default();
+
+                        let text = egui::RichText::new(format!(
+                            "{}{}  (best was {})",
+                            move_name(&history, &review, i + 1),
+                            quality.symbol(),
+                            best
+                        ))
+                        .color(quality_color(quality));
+
+                        // goes to the position after the bad move
+                        if ui.selectable_label(false, text).clicked() {
+                            clicked = Some(i + 1);
+                        }
+                    }
+                });
+        });
+
+    let Some(node) = clicked.and_then(|i| review.nodes.get(i).copied()) else {
+        return;
+    };
+
+    // moves deleted in the analysis can't be gone to anymore
+    if history.contains(node) {
+        history.select(node);
+    }
+}
+
+// White's winning chances over the game, white's part at the bottom like the eval bar.
+// Gives back the position that was clicked
+fn evaluation_graph(
+    ui: &mut egui::Ui,
+    review: &GameReview,
+    history: &MoveHistory,
+) -> Option<usize> {
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), 120.0),
+        egui::Sense::click(),
+    );
+    let rect = response.rect;
+    let count = review.boards.len();
+
+    let x = |i: usize| rect.left() + rect.width() * i as f32 / (count - 1).max(1) as f32;
+    let y = |share: f32| rect.bottom() - rect.height() * share;
+
+    painter.rect_filled(rect, 2.0, egui::Color32::from_gray(50));
+
+    let points: Vec<egui::Pos2> = review
+        .scores
+        .iter()
+        .enumerate()
+        .map(|(i, score)| egui::pos2(x(i), y(score.map_or(0.5, |s| s.white_share()))))
+        .collect();
+
+    // one slice per move, the area under the curve isn't convex as a whole
+    for pair in points.windows(2) {
+        painter.add(egui::Shape::convex_polygon(
+            vec![
+                pair[0],
+                pair[1],
+                egui::pos2(pair[1].x, rect.bottom()),
+                egui::pos2(pair[0].x, rect.bottom()),
+            ],
+            egui::Color32::from_gray(235),
+            egui::Stroke::NONE,
+        ));
+    }
+
+    painter.line_segment(
+        [
+            egui::pos2(rect.left(), rect.center().y),
+            egui::pos2(rect.right(), rect.center().y),
+        ],
+        egui::Stroke::new(1.0, egui::Color32::GRAY),
+    );
+
+    // where the board is right now
+    let current = history.current_node();
+
+    if let Some(i) = review.nodes.iter().position(|&node| node == current) {
+        painter.line_segment(
+            [
+                egui::pos2(x(i), rect.top()),
+                egui::pos2(x(i), rect.bottom()),
+            ],
+            egui::Stroke::new(1.0, egui::Color32::from_rgb(80, 140, 230)),
+        );
+    }
+
+    for i in 0..points.len().saturating_sub(1) {
+        if let Some(quality) = review.quality(i).filter(|q| *q != MoveQuality::Good) {
+            painter.circle_filled(points[i + 1], 3.5, quality_color(quality));
+        }
+    }
+
+    let hovered = response
+        .hover_pos()
+        .map(|pos| ((pos.x - rect.left()) / rect.width() * (count - 1) as f32).round() as usize)
+        .map(|i| i.min(count - 1));
+
+    let clicked = hovered.filter(|_| response.clicked());
+
+    if let Some(i) = hovered {
+        let score = review
+            .scores
+            .get(i)
+            .copied()
+            .flatten()
+            .map_or("...".to_string(), |score| score.text());
+
+        response.on_hover_text(format!("{}  {}", move_name(history, review, i), score));
+    }
+
+    clicked
+}
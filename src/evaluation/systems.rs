@@ -0,0 +1,402 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, futures::check_ready},
+};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    engine::{
+        board::{Board, tile},
+        external::ExternalEngine,
+        search::{Score, best_move},
+    },
+    evaluation::resources::*,
+    game::{
+        annotations::AnnotationGizmos,
+        resources::{
+            BoardLayout, BoardOrientation, GameSetup, GameState, MoveHistory, MoveInput, PlayerKind,
+        },
+    },
+    settings::resources::Settings,
+    states::AppState,
+};
+
+// Runs on the task pool. The external engine from the settings is used when there is one,
+// the built-in search (to depth) otherwise
+pub fn analyse(
+    board: Board,
+    engine_path: &str,
+    process: &EngineProcess,
+    table: &AnalysisTable,
+    depth: u8,
+    milliseconds: u64,
+) -> Result<EngineAnswer, String> {
+    // no moves means the game is over, nothing to search
+    if board.legal_moves().is_empty() {
+        let score = if board.in_check() {
+            Score::Checkmated(board.turn)
+        } else {
+            Score::Centipawns(0)
+        };
+
+        return Ok(EngineAnswer {
+            best_move: None,
+            score: Some(score),
+            engine: "Game over".to_string(),
+        });
+    }
+
+    if engine_path.trim().is_empty() {
+        let best = best_move(&board, depth, &table.0);
+
+        return Ok(EngineAnswer {
+            best_move: best.map(|(mv, _)| mv),
+            score: best.map(|(_, score)| Score::from_search(score, board.turn)),
+            engine: "Built-in".to_string(),
+        });
+    }
+
+    let mut engine = process
+        .0
+        .lock()
+        .map_err(|_| "the engine crashed earlier".to_string())?;
+
+    // started the first time it's needed, and again after the path was changed or it stopped working
+    if engine.as_ref().is_none_or(|e| e.path != engine_path) {
+        *engine = Some(ExternalEngine::start(engine_path)?);
+    }
+
+    let Some(external) = engine.as_mut() else {
+        return Err("no engine".to_string());
+    };
+
+    let result = external
+        .go(&board, milliseconds)
+        .map(|(best_move, score)| EngineAnswer {
+            best_move,
+            score,
+            engine: external.name.clone(),
+        });
+
+    if result.is_err() {
+        *engine = None;
+    }
+
+    result
+}
+
+pub fn eval_bar_enabled(settings: Res<Settings>) -> bool {
+    settings.eval_bar
+}
+
+// Starts a search whenever the shown position is one there's no evaluation for yet.
+// A search for a position that isn't shown anymore gets dropped
+pub fn start_evaluation(
+    settings: Res<Settings>,
+    game_state: Res<GameState>,
+    history: Res<MoveHistory>,
+    process: Res<EngineProcess>,
+    table: Res<AnalysisTable>,
+    evaluation: Res<Evaluation>,
+    mut task: ResMut<EvaluationTask>,
+) {
+    // an old position from the move list gets its own evaluation
+    let board = history
+        .viewed_position()
+        .copied()
+        .unwrap_or(game_state.board);
+
+    if task.board == Some(board) || (task.task.is_none() && evaluation.board == Some(board)) {
+        return;
+    }
+
+    let engine_path = settings.engine_path.clone();
+    let process = process.clone();
+    let table = table.clone();
+
+    task.board = Some(board);
+    task.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        analyse(
+            board,
+            &engine_path,
+            &process,
+            &table,
+            BUILT_IN_DEPTH,
+            ENGINE_MOVE_TIME,
+        )
+    }));
+}
+
+pub fn finish_evaluation(mut task: ResMut<EvaluationTask>, mut evaluation: ResMut<Evaluation>) {
+    let Some(running) = task.task.as_mut() else {
+        return;
+    };
+
+    let Some(result) = check_ready(running) else {
+        return;
+    };
+
+    // the board is kept even when it failed, so it isn't tried again every frame
+    evaluation.board = task.board.take();
+    task.task = None;
+
+    match result {
+        Ok(answer) => {
+            evaluation.answer = Some(answer);
+            evaluation.error = None;
+        }
+        Err(error) => {
+            println!("Evaluation failed: {}", error);
+            evaluation.answer = None;
+            evaluation.error = Some(error);
+        }
+    }
+}
+
+// Drawn with egui in the gap between the clock panel and the board, as tall as the squares.
+// White's part grows from white's side of the board
+pub fn eval_bar_ui(
+    mut contexts: EguiContexts,
+    evaluation: Res<Evaluation>,
+    layout: Res<BoardLayout>,
+    orientation: Res<BoardOrientation>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    let center_y = layout.viewport_position.y + layout.viewport_size.y / 2.0;
+    let half_height = layout.tile_size * 4.0;
+    let rect = egui::Rect::from_min_max(
+        egui::pos2(
+            layout.viewport_position.x - EVAL_BAR_WIDTH + 6.0,
+            center_y - half_height,
+        ),
+        egui::pos2(layout.viewport_position.x - 6.0, center_y + half_height),
+    );
+
+    let score = evaluation.answer.as_ref().and_then(|answer| answer.score);
+    let white_share = score.map_or(0.5, |score| score.white_share());
+
+    egui::Area::new(egui::Id::new("eval_bar"))
+        .fixed_pos(rect.min)
+        .order(egui::Order::Background)
+        .show(ctx, |ui| {
+            let (response, painter) = ui.allocate_painter(rect.size(), egui::Sense::hover());
+            let rect = response.rect;
+
+            painter.rect_filled(rect, 2.0, egui::Color32::from_gray(50));
+
+            let white_height = rect.height() * white_share;
+            let white_rect = if orientation.flipped {
+                egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), white_height))
+            } else {
+                egui::Rect::from_min_max(
+                    egui::pos2(rect.min.x, rect.max.y - white_height),
+                    rect.max,
+                )
+            };
+
+            painter.rect_filled(white_rect, 2.0, egui::Color32::from_gray(235));
+
+            // the number goes on the end of whoever is ahead, in the other color so it can be read
+            if let Some(score) = score {
+                let white_ahead = white_share >= 0.5;
+                let (position, align) = if white_ahead != orientation.flipped {
+                    (
+                        egui::pos2(rect.center().x, rect.max.y - 3.0),
+                        egui::Align2::CENTER_BOTTOM,
+                    )
+                } else {
+                    (
+                        egui::pos2(rect.center().x, rect.min.y + 3.0),
+                        egui::Align2::CENTER_TOP,
+                    )
+                };
+                let color = if white_ahead {
+                    egui::Color32::from_gray(40)
+                } else {
+                    egui::Color32::from_gray(220)
+                };
+
+                painter.text(
+                    position,
+                    align,
+                    score.text(),
+                    egui::FontId::proportional(9.0),
+                    color,
+                );
+            }
+
+            if let Some(error) = &evaluation.error {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    "!",
+                    egui::FontId::proportional(14.0),
+                    egui::Color32::RED,
+                );
+                response.on_hover_text(error);
+            } else if let Some(answer) = &evaluation.answer {
+                let text = match answer.score {
+                    Some(score) => format!("{} ({})", score.text(), answer.engine),
+                    None => answer.engine.clone(),
+                };
+                response.on_hover_text(text);
+            }
+        });
+}
+
+pub fn hint_key(
+    keys: Res<ButtonInput<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut hint_requests: EventWriter<RequestHint>,
+) {
+    // an H typed into the move field isn't asking for a hint
+    if keys.just_pressed(KeyCode::KeyH) && !move_input.typing {
+        hint_requests.write(RequestHint);
+    }
+}
+
+// Shows the next bit of the hint, the search only runs the first time for a position
+#[allow(clippy::too_many_arguments)]
+pub fn request_hint(
+    mut events: EventReader<RequestHint>,
+    mut hint: ResMut<Hint>,
+    mut task: ResMut<HintTask>,
+    history: Res<MoveHistory>,
+    game_state: Res<GameState>,
+    setup: Res<GameSetup>,
+    state: Res<State<AppState>>,
+    settings: Res<Settings>,
+    process: Res<EngineProcess>,
+    table: Res<AnalysisTable>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    // no hints for finished games, old positions or the computer's moves
+    if *state.get() == AppState::GameOver
+        || game_state.is_over()
+        || history.viewing.is_some()
+        || (*state.get() == AppState::Playing && setup.player(game_state.turn) != PlayerKind::Human)
+    {
+        println!("No hint for this position");
+        return;
+    }
+
+    let board = game_state.board;
+
+    // showing the whole move is still the same hint, it was counted when the piece was shown
+    if hint.board == Some(board) && hint.best_move.is_some() {
+        if hint.level == HintLevel::Piece {
+            hint.level = HintLevel::Move;
+        }
+
+        return;
+    }
+
+    // still working on it
+    if task.board == Some(board) {
+        return;
+    }
+
+    let engine_path = settings.engine_path.clone();
+    let process = process.clone();
+    let table = table.clone();
+
+    task.board = Some(board);
+    task.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        analyse(
+            board,
+            &engine_path,
+            &process,
+            &table,
+            HINT_DEPTH,
+            HINT_MOVE_TIME,
+        )
+    }));
+    hint.searching = true;
+}
+
+pub fn finish_hint(
+    mut task: ResMut<HintTask>,
+    mut hint: ResMut<Hint>,
+    mut history: ResMut<MoveHistory>,
+    game_state: Res<GameState>,
+) {
+    let Some(running) = task.task.as_mut() else {
+        return;
+    };
+
+    let Some(result) = check_ready(running) else {
+        return;
+    };
+
+    let board = task.board.take();
+    task.task = None;
+    hint.searching = false;
+
+    // the board moved on before the search was done
+    if board != Some(game_state.board) {
+        return;
+    }
+
+    match result {
+        Ok(EngineAnswer {
+            best_move: Some(mv),
+            ..
+        }) => {
+            hint.board = board;
+            hint.best_move = Some(mv);
+            hint.level = HintLevel::Piece;
+            history.hints += 1;
+        }
+        Ok(_) => println!("No hint, there's nothing to play"),
+        Err(error) => println!("Couldn't get a hint: {}", error),
+    }
+}
+
+// A hint only belongs to the position it was asked for
+pub fn clear_hint(mut hint: ResMut<Hint>, game_state: Res<GameState>) {
+    if hint.board.is_some() && hint.board != Some(game_state.board) {
+        hint.board = None;
+        hint.best_move = None;
+        hint.level = HintLevel::Hidden;
+    }
+}
+
+// Same look as the circles and arrows drawn with right click, just in its own color
+pub fn draw_hint(
+    mut gizmos: Gizmos<AnnotationGizmos>,
+    hint: Res<Hint>,
+    history: Res<MoveHistory>,
+    layout: Res<BoardLayout>,
+) {
+    let Some(mv) = hint.best_move else {
+        return;
+    };
+
+    if history.viewing.is_some() {
+        return;
+    }
+
+    let tile_size = layout.tile_size;
+    let start = layout.tile_to_world(tile(mv.from));
+
+    gizmos.circle_2d(
+        Isometry2d::from_translation(start),
+        tile_size * 0.42,
+        HINT_COLOR,
+    );
+
+    if hint.level == HintLevel::Move {
+        // castling points at where the king ends up, not at its rook
+        let to = hint.board.map_or(mv.to, |board| board.destination(mv));
+        let end = layout.tile_to_world(tile(to));
+        let end = end - (end - start).normalize_or_zero() * tile_size * 0.2;
+
+        gizmos
+            .arrow_2d(start, end, HINT_COLOR)
+            .with_tip_length(tile_size * 0.35);
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::game::{resources::*, systems::cursor_world_position};
+
+// Own gizmo group so the arrows can be made thicker than the default lines
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct AnnotationGizmos;
+
+// Right click on a square circles it, right drag from one square to another draws an arrow.
+// Left click on the board takes everything away again. It all belongs to the position that's shown
+pub fn annotation_input(
+    q_window: Query<&Window>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    layout: Res<BoardLayout>,
+    mut history: ResMut<MoveHistory>,
+    mut drawing_from: Local<Option<(u8, u8)>>,
+) {
+    let clear = buttons.just_pressed(MouseButton::Left);
+    let start = buttons.just_pressed(MouseButton::Right);
+    let end = buttons.just_released(MouseButton::Right);
+
+    if !clear && !start && !end {
+        return;
+    }
+
+    let tile = cursor_world_position(&q_window, &q_camera).and_then(|p| layout.world_to_tile(p));
+    let current = history.current_node();
+
+    // only touch the history when there's something to remove, everything showing it redraws
+    if clear && tile.is_some() && history.annotations.contains_key(&current) {
+        history.annotations.remove(&current);
+    }
+
+    if start {
+        *drawing_from = tile;
+    }
+
+    if !end {
+        return;
+    }
+
+    let (Some(from), Some(to)) = (drawing_from.take(), tile) else {
+        return;
+    };
+
+    let color = AnnotationColor::from_keys(&keys);
+    let annotations = history.annotations.entry(current).or_default();
+
+    if from == to {
+        annotations.toggle_square(MarkedSquare { tile: to, color });
+    } else {
+        annotations.toggle_arrow(Arrow { from, to, color });
+    }
+
+    if annotations.is_empty() {
+        history.annotations.remove(&current);
+    }
+}
+
+// Gizmo lines are as wide in pixels as they're set, so they get scaled with the board
+pub fn scale_annotation_gizmos(
+    layout: Res<BoardLayout>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    if !layout.is_changed() {
+        return;
+    }
+
+    let (config, _) = config_store.config_mut::<AnnotationGizmos>();
+    config.line.width = layout.tile_size * 0.12;
+}
+
+// Gizmos are drawn every frame and on top of the sprites, so there's nothing to spawn or clean up
+pub fn draw_annotations(
+    mut gizmos: Gizmos<AnnotationGizmos>,
+    history: Res<MoveHistory>,
+    layout: Res<BoardLayout>,
+) {
+    let Some(annotations) = history.annotations.get(&history.current_node()) else {
+        return;
+    };
+
+    let tile_size = layout.tile_size;
+
+    for marked in annotations.squares.iter() {
+        gizmos.circle_2d(
+            Isometry2d::from_translation(layout.tile_to_world(marked.tile)),
+            tile_size * 0.42,
+            marked.color.color(),
+        );
+    }
+
+    for arrow in annotations.arrows.iter() {
+        let start = layout.tile_to_world(arrow.from);
+        let end = layout.tile_to_world(arrow.to);
+
+        // stop a bit before the middle of the square so the tip doesn't cover the whole piece
+        let end = end - (end - start).normalize_or_zero() * tile_size * 0.2;
+
+        gizmos
+            .arrow_2d(start, end, arrow.color.color())
+            .with_tip_length(tile_size * 0.35);
+    }
+}
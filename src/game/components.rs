@@ -1 +1,55 @@
+use bevy::prelude::*;
 
+// The two tinted tiles showing where the last move came from and went to
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum LastMoveHighlight {
+    From,
+    To,
+}
+
+// The two tiles of a queued premove
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum PremoveHighlight {
+    From,
+    To,
+}
+
+// Red tile under the king of the side to move while it's in check
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct CheckHighlight;
+
+// Slides a piece sprite from start to end, removed again once the timer finishes
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct PieceAnimation {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub timer: Timer,
+}
+
+// Captured pieces fade and shrink for a bit before they get despawned
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct CaptureAnimation {
+    pub start_scale: Vec3,
+    pub timer: Timer,
+}
+
+// Sprites showing an older position from the move list, the real pieces
+// are hidden while these are around
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct PreviewPiece;
+
+// Everything in the captured pieces trays (sprites and the material text)
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct CapturedTray;
+
+// The a-h / 1-8 labels, they're all spawned again when the style, orientation or layout changes
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct CoordinateLabel;
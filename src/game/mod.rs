@@ -1,16 +1,34 @@
 use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+use std::collections::HashMap;
 
+pub mod annotations;
 pub mod components;
+mod pgn;
 pub mod resources;
 mod systems;
 
+use annotations::*;
+use components::*;
 use resources::*;
 use systems::*;
 
-use crate::pieces::components::PieceColor;
+// the editor needs the mouse on the board too
+pub use systems::cursor_world_position;
+
+use crate::{
+    clock::resources::{IncrementMode, TimeControlPreset},
+    engine::{board::Board, variants::Variant},
+    states::{AppState, board_active},
+};
 
 pub struct GamePlugin;
 
+// The chain from clicking a tile to the move being recorded,
+// other plugins that react to MoveMade run after it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MoveSet;
+
 // Important to insert and add everything
 // Also no idea why the inser_resource(Selections) doesn't have
 // any color highlighting inside the brackets
@@ -19,31 +37,163 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Selections>()
             .register_type::<GameState>()
+            .register_type::<DraggedPiece>()
+            .register_type::<AnimationSettings>()
+            .register_type::<BoardOrientation>()
+            .register_type::<MoveHistory>()
+            .register_type::<MoveNode>()
+            .register_type::<GameOverDialog>()
+            .register_type::<LastMoveHighlight>()
+            .register_type::<CheckHighlight>()
+            .register_type::<PremoveHighlight>()
+            .register_type::<PieceAnimation>()
+            .register_type::<CaptureAnimation>()
+            .register_type::<PreviewPiece>()
+            .register_type::<CapturedTray>()
+            .register_type::<GameSetup>()
+            .register_type::<BoardLayout>()
+            .register_type::<CoordinateLabels>()
+            .register_type::<CoordinateLabel>()
+            .register_type::<MoveInput>()
+            .register_type::<AnnotationColor>()
+            .register_type::<PositionAnnotations>()
+            .init_gizmo_group::<AnnotationGizmos>()
             .insert_resource(CurrentTile((0, 0)))
+            // same size as the window in main.rs, gets updated once the window is there
+            .insert_resource(BoardLayout::new(Vec2::new(1280.0, 900.0)))
             .insert_resource(Selections {
                 selected_piece: None,
                 second_selected_piece: None,
                 second_selected_tile: None,
+                premove: None,
+            })
+            .insert_resource(DraggedPiece {
+                entity: None,
+                from: (0, 0),
+                dropped: false,
+            })
+            .insert_resource(MoveInput {
+                text: String::new(),
+                submitted: None,
+                error: None,
+                typing: false,
+            })
+            .insert_resource(CoordinateLabels {
+                style: CoordinateStyle::Edges,
+            })
+            .insert_resource(BoardOrientation {
+                flipped: false,
+                auto_flip: false,
+            })
+            .insert_resource(MoveHistory {
+                nodes: Vec::new(),
+                line: Vec::new(),
+                moves: Vec::new(),
+                positions: Vec::new(),
+                cursor: 0,
+                viewing: None,
+                annotations: HashMap::new(),
+                hints: 0,
             })
-            .insert_resource(GameState {
-                turn: PieceColor::White,
-                check: false,
-                checkmate: false,
-                stalemate: false,
+            .insert_resource(GameOverDialog { message: None })
+            .insert_resource(AnimationSettings {
+                move_duration: 0.2,
+                capture_duration: 0.25,
+            })
+            .insert_resource(GameState::new(Board::start()))
+            .insert_resource(GameSetup {
+                white: PlayerKind::Human,
+                black: PlayerKind::Computer,
+                computer_depth: 3,
+                time_control: Some(TimeControlPreset::Rapid.time_control(IncrementMode::Fischer)),
+                start_fen: String::new(),
+                variant: Variant::Standard,
             })
             .add_event::<CurrentTile>()
             .add_event::<MovePiece>()
             .add_event::<MoveMade>()
             .add_event::<Castle>()
+            .add_event::<IllegalMove>()
+            .add_event::<NewGame>()
+            .add_event::<JumpToPosition>()
+            // the board only reacts to input while there's a game on it,
+            // new games can be started from the menus so reset_game always runs
+            .configure_sets(Update, MoveSet.run_if(board_active))
+            .add_systems(Startup, set_up_highlights)
+            .add_systems(
+                Update,
+                (update_board_layout, reset_game, jump_to_position).before(MoveSet),
+            )
             .add_systems(
                 Update,
+                // chained so a drop is handled before the move it submits
                 (
-                    mouse_input,
+                    mouse_input.run_if(human_at_board),
                     tile_clicked,
+                    drag_piece,
+                    drop_piece,
+                    submit_typed_move,
+                    play_premove,
                     move_piece,
-                    update_ui,
-                    move_made,
                     castle,
+                    settle_dropped_piece,
+                    move_made,
+                    record_move,
+                    highlight_last_move,
+                    highlight_check,
+                    highlight_premove,
+                    animate_pieces,
+                    animate_captures,
+                )
+                    .chain()
+                    .in_set(MoveSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    (flip_board_input, coordinate_labels_input)
+                        .run_if(board_active.and(not_typing)),
+                    auto_flip_board,
+                    apply_orientation,
+                    update_coordinate_labels,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                // after the moves so a move that was just made is in the history already
+                (
+                    move_list_keys.run_if(board_active.and(not_typing)),
+                    follow_viewed_position.run_if(in_state(AppState::Analysis)),
+                    show_viewed_position,
+                    update_captured_tray,
+                )
+                    .chain()
+                    .after(MoveSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    annotation_input.run_if(board_active),
+                    scale_annotation_gizmos,
+                    draw_annotations.run_if(board_active),
+                )
+                    .chain()
+                    .after(MoveSet),
+            )
+            .add_systems(
+                Update,
+                check_game_over
+                    .after(MoveSet)
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_systems(OnEnter(AppState::GameOver), clear_game_over_dialog)
+            .add_systems(
+                EguiContextPass,
+                (
+                    track_typing,
+                    update_ui.run_if(board_active),
+                    game_over_dialog.run_if(in_state(AppState::GameOver)),
                 ),
             );
     }
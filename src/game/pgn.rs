@@ -0,0 +1,251 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    engine::{
+        board::{Board, square},
+        fen::square_name,
+    },
+    game::resources::{GameSetup, GameState, MoveHistory, MoveNode, PositionAnnotations},
+    pieces::components::PieceColor,
+};
+
+const SAVE_DIRECTORY: &str = "saved_games";
+
+// "1-0", "0-1", "1/2-1/2" or "*" while the game is still going
+pub fn result_string(game_state: &GameState) -> &'static str {
+    if game_state.stalemate || (game_state.timeout && game_state.insufficient_material) {
+        "1/2-1/2"
+    } else if game_state.checkmate || game_state.timeout {
+        // the side to move is the one that got mated / flagged
+        match game_state.turn {
+            PieceColor::White => "0-1",
+            PieceColor::Black => "1-0",
+        }
+    } else {
+        "*"
+    }
+}
+
+// Human readable version of the result for the game over dialog
+pub fn result_reason(game_state: &GameState) -> String {
+    let winner = game_state.turn.opposite();
+
+    if game_state.checkmate {
+        format!("{:?} wins by checkmate", winner)
+    } else if game_state.stalemate {
+        "Draw by stalemate".to_string()
+    } else if game_state.timeout && game_state.insufficient_material {
+        "Draw, time ran out but there's not enough material to mate".to_string()
+    } else if game_state.timeout {
+        format!("{:?} wins on time", winner)
+    } else {
+        "Game in progress".to_string()
+    }
+}
+
+pub fn game_to_pgn(history: &MoveHistory, setup: &GameSetup, result: &str) -> String {
+    let mut pgn = String::new();
+
+    for (tag, value) in [
+        ("Event", "Casual game"),
+        ("Site", "Chess in Rust"),
+        ("Date", &today()),
+        ("Round", "-"),
+        ("White", &setup.player_name(PieceColor::White)),
+        ("Black", &setup.player_name(PieceColor::Black)),
+        ("Result", result),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+
+    // for training, how much help there was
+    if history.hints > 0 {
+        pgn.push_str(&format!("[Hints \"{}\"]\n", history.hints));
+    }
+
+    let start = history
+        .nodes
+        .first()
+        .map(|root| root.board)
+        .unwrap_or_default();
+
+    // games from a FEN or chess960 need the starting position in the tags
+    if start != Board::start() {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", start.to_fen()));
+    }
+
+    pgn.push('\n');
+
+    // PGN lines shouldn't be longer than 80 characters
+    let mut line = String::new();
+    let mut tokens: Vec<String> = Vec::new();
+
+    // arrows on the starting position go in front of the first move
+    tokens.extend(history.annotations.get(&0).and_then(annotation_comment));
+
+    if !history.nodes.is_empty() {
+        line_tokens(history, 0, true, &mut tokens);
+    }
+
+    tokens.push(result.to_string());
+
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.len() + 1 > 80 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+
+        line.push_str(&token);
+    }
+
+    pgn.push_str(&line);
+    pgn.push('\n');
+
+    pgn
+}
+
+// Everything after the node: the main continuation, with the side variations in brackets (RAVs)
+// right after the main move they replace. Those can have variations of their own
+fn line_tokens(
+    history: &MoveHistory,
+    node: usize,
+    mut show_number: bool,
+    tokens: &mut Vec<String>,
+) {
+    let mut parent = node;
+
+    while let Some((&main, variations)) = history.nodes[parent].children.split_first() {
+        move_tokens(history, main, show_number, tokens);
+
+        for &variation in variations {
+            let mut variation_tokens = Vec::new();
+
+            move_tokens(history, variation, true, &mut variation_tokens);
+            line_tokens(history, variation, false, &mut variation_tokens);
+
+            if let Some(first) = variation_tokens.first_mut() {
+                first.insert(0, '(');
+            }
+
+            if let Some(last) = variation_tokens.last_mut() {
+                last.push(')');
+            }
+
+            tokens.extend(variation_tokens);
+        }
+
+        // after a variation black's move needs its number again ("5... Nf6")
+        show_number = !variations.is_empty();
+        parent = main;
+    }
+}
+
+// The move number, the move and the comment with its arrows.
+// Black only gets a number ("1...") at the start of a line
+fn move_tokens(history: &MoveHistory, node: usize, show_number: bool, tokens: &mut Vec<String>) {
+    let MoveNode {
+        record: Some(record),
+        parent: Some(parent),
+        ..
+    } = &history.nodes[node]
+    else {
+        return;
+    };
+
+    let number = history.nodes[*parent].board.fullmove_number;
+
+    if record.color == PieceColor::White {
+        tokens.push(format!("{}.", number));
+    } else if show_number {
+        tokens.push(format!("{}...", number));
+    }
+
+    tokens.push(record.san.clone());
+    tokens.extend(history.annotations.get(&node).and_then(annotation_comment));
+}
+
+// Arrows and circles the way lichess / chess.com write them: {[%csl Gd4,Re5][%cal Gg1f3]}
+fn annotation_comment(annotations: &PositionAnnotations) -> Option<String> {
+    if annotations.is_empty() {
+        return None;
+    }
+
+    let mut comment = String::from("{");
+
+    if !annotations.squares.is_empty() {
+        let squares: Vec<String> = annotations
+            .squares
+            .iter()
+            .map(|s| format!("{}{}", s.color.letter(), square_name(square(s.tile))))
+            .collect();
+
+        comment.push_str(&format!("[%csl {}]", squares.join(",")));
+    }
+
+    if !annotations.arrows.is_empty() {
+        let arrows: Vec<String> = annotations
+            .arrows
+            .iter()
+            .map(|a| {
+                format!(
+                    "{}{}{}",
+                    a.color.letter(),
+                    square_name(square(a.from)),
+                    square_name(square(a.to))
+                )
+            })
+            .collect();
+
+        comment.push_str(&format!("[%cal {}]", arrows.join(",")));
+    }
+
+    comment.push('}');
+    Some(comment)
+}
+
+// Saves into saved_games/ next to wherever the game was started from
+pub fn save_pgn(pgn: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(SAVE_DIRECTORY)?;
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let path = PathBuf::from(SAVE_DIRECTORY).join(format!("game_{}.pgn", seconds));
+    fs::write(&path, pgn)?;
+
+    Ok(path)
+}
+
+// PGN wants the date as YYYY.MM.DD, this turns days since 1970 into a date
+// (Howard Hinnant's days_from_civil the other way around)
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
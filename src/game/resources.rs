@@ -1,9 +1,98 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-use crate::pieces::components::PieceColor;
+use crate::{
+    clock::resources::{CLOCK_PANEL_WIDTH, TimeControl},
+    engine::{
+        board::{Board, Piece},
+        variants::Variant,
+    },
+    evaluation::resources::EVAL_BAR_WIDTH,
+    pieces::components::{PieceColor, PieceType},
+};
 
-pub const TILE_SIZE: f32 = 89.5;
-pub const BOARD_OFFSET: Vec2 = Vec2::new(135.0, 135.0);
+// The colors of the board, pieces and highlights come from the theme (see theme/resources.rs)
+
+// frame < squares < highlights < coordinates < pieces
+pub const FRAME_Z: f32 = -0.2;
+pub const SQUARE_Z: f32 = -0.1;
+pub const LABEL_Z: f32 = -0.04;
+
+// Piece images are 128x128
+pub const PIECE_IMAGE_SIZE: f32 = 128.0;
+
+// Highlights sit between the board (z = -0.1) and the pieces (z = 0.0)
+pub const HIGHLIGHT_Z: f32 = -0.05;
+
+// Dragged pieces are drawn above everything else on the board
+pub const DRAG_Z: f32 = 1.0;
+
+pub const SIDE_PANEL_WIDTH: f32 = 200.0;
+
+// Captured pieces are shown small in the border below / above the board,
+// both are in tiles so they grow and shrink with the board
+pub const TRAY_PIECE_SIZE: f32 = 0.43;
+pub const TRAY_SPACING: f32 = 0.29;
+
+// Where the board is on screen and how big it is, recomputed when the window changes size.
+// The board is always centered on (0, 0) in the world. The camera only draws the area
+// between the side panels (viewport, in logical pixels) and the board gets scaled to fit in there
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct BoardLayout {
+    pub tile_size: f32,
+    pub viewport_position: Vec2,
+    pub viewport_size: Vec2,
+}
+
+impl BoardLayout {
+    pub fn new(window_size: Vec2) -> BoardLayout {
+        // the evaluation bar goes in the gap between the clock panel and the board
+        let viewport_position = Vec2::new(CLOCK_PANEL_WIDTH + EVAL_BAR_WIDTH, 0.0);
+        let viewport_size = Vec2::new(
+            window_size.x - CLOCK_PANEL_WIDTH - EVAL_BAR_WIDTH - SIDE_PANEL_WIDTH,
+            window_size.y,
+        )
+        .max(Vec2::ONE);
+
+        BoardLayout {
+            // 8 tiles for the board plus one on each side for the captured pieces
+            tile_size: viewport_size.min_element() / 10.0,
+            viewport_position,
+            viewport_size,
+        }
+    }
+
+    // Center of the tile in world coordinates
+    pub fn tile_to_world(&self, tile: (u8, u8)) -> Vec2 {
+        Vec2::new(
+            (tile.0 as f32 - 4.5) * self.tile_size,
+            (tile.1 as f32 - 4.5) * self.tile_size,
+        )
+    }
+
+    // None when the position is outside of the board
+    pub fn world_to_tile(&self, world: Vec2) -> Option<(u8, u8)> {
+        let file = (world.x / self.tile_size + 4.0).floor();
+        let rank = (world.y / self.tile_size + 4.0).floor();
+
+        if (0.0..8.0).contains(&file) && (0.0..8.0).contains(&rank) {
+            Some((file as u8 + 1, rank as u8 + 1))
+        } else {
+            None
+        }
+    }
+
+    pub fn piece_scale(&self) -> f32 {
+        self.tile_size / PIECE_IMAGE_SIZE
+    }
+
+    // The frame around the squares is one tile wide on every side
+    pub fn frame_size(&self) -> f32 {
+        self.tile_size * 10.0
+    }
+}
 
 // Used as an event and tracks the current tile when the mouse is clicked
 #[derive(Resource, Reflect, Default, Debug, Clone, Event)]
@@ -19,29 +108,537 @@ pub struct Selections {
 
     pub second_selected_piece: Option<Entity>,
     pub second_selected_tile: Option<(u8, u8)>,
+
+    pub premove: Option<Premove>,
+}
+
+// A move queued while the computer is thinking, it's played as soon as it's our turn
+// if the same piece is still on from and the move is legal then
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct Premove {
+    pub piece: Entity,
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+}
+
+// The piece that's currently held with the mouse (if any)
+// from is where it gets put back if the drop wasn't a legal move
+// dropped is set for the one frame after the mouse button was released
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct DraggedPiece {
+    pub entity: Option<Entity>,
+    pub from: (u8, u8),
+    pub dropped: bool,
+}
+
+// The move typed into the side panel. submitted is set when enter gets pressed,
+// error stays under the field until the text is changed or a move goes through.
+// typing is set while any text field has the keyboard so the shortcuts don't go off
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct MoveInput {
+    pub text: String,
+    pub submitted: Option<String>,
+    pub error: Option<String>,
+    pub typing: bool,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CoordinateStyle {
+    Hidden,
+    // a-h and 1-8 in the frame around the board
+    #[default]
+    Edges,
+    // in the corners of the squares on the bottom rank / left file
+    InsideSquares,
+}
+
+// C goes through the styles
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct CoordinateLabels {
+    pub style: CoordinateStyle,
+}
+
+// Which side is at the bottom of the screen
+// auto_flip turns the board towards whoever has to move (for hot-seat games)
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct BoardOrientation {
+    pub flipped: bool,
+    pub auto_flip: bool,
+}
+
+impl BoardOrientation {
+    // Rotation for the camera, and for sprites so they don't end up upside down
+    pub fn rotation(&self) -> Quat {
+        if self.flipped {
+            Quat::from_rotation_z(std::f32::consts::PI)
+        } else {
+            Quat::IDENTITY
+        }
+    }
+}
+
+// How long the move / capture animations take (in seconds)
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct AnimationSettings {
+    pub move_duration: f32,
+    pub capture_duration: f32,
 }
 
 // Again an event with some data
+// promotion is what a pawn turns into on the last rank, None means a queen.
+// computer is set for the moves the AI plays, the human can't move for it
 #[derive(Resource, Reflect, Event)]
 pub struct MovePiece {
     pub piece: Entity,
     pub from: (u8, u8),
     pub to: (u8, u8),
+    pub promotion: Option<PieceType>,
+    pub computer: bool,
 }
 
+// A move that was tried but isn't legal (clicked, dropped or typed)
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct IllegalMove;
+
+// The rook's half of a castling move, the king is moved by move_piece
 #[derive(Resource, Reflect, Event)]
 pub struct Castle {
-    pub king_pos: (u8, u8),
+    pub color: PieceColor,
+    pub rook_from: (u8, u8),
+    pub rook_to: (u8, u8),
+}
+
+// Throws away the current game and sets up a new one starting from board
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct NewGame {
+    pub board: Board,
+}
+
+// Puts the board on another position of the same game (moving through an analysis)
+// without touching the move history
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct JumpToPosition {
+    pub board: Board,
 }
 
+// Sent once a move went through, with the tiles the piece moved between
 #[derive(Resource, Reflect, Event)]
-pub struct MoveMade();
+pub struct MoveMade {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub promotion: Option<PieceType>,
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PlayerKind {
+    #[default]
+    Human,
+    Computer,
+}
+
+// Everything picked on the game setup screen.
+// start_fen is only used when it's not empty, otherwise the variant decides the position
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct GameSetup {
+    pub white: PlayerKind,
+    pub black: PlayerKind,
+    // how many moves (plies) ahead the computer looks
+    pub computer_depth: u8,
+    // None plays without a clock
+    pub time_control: Option<TimeControl>,
+    pub start_fen: String,
+    pub variant: Variant,
+}
 
+impl GameSetup {
+    pub fn player(&self, color: PieceColor) -> PlayerKind {
+        match color {
+            PieceColor::White => self.white,
+            PieceColor::Black => self.black,
+        }
+    }
+
+    // For the PGN tags, "Human" or "Computer (depth 3)"
+    pub fn player_name(&self, color: PieceColor) -> String {
+        match self.player(color) {
+            PlayerKind::Human => "Human".to_string(),
+            PlayerKind::Computer => format!("Computer (depth {})", self.computer_depth),
+        }
+    }
+
+    pub fn starting_board(&self) -> Result<Board, String> {
+        if self.start_fen.trim().is_empty() {
+            Ok(self.variant.starting_board())
+        } else {
+            Board::from_fen(self.start_fen.trim()).and_then(Board::playable)
+        }
+    }
+}
+
+// board is the actual position, the rest is what the UI shows about it
 #[derive(Resource, Reflect, Default)]
 #[reflect(Resource)]
 pub struct GameState {
+    pub board: Board,
     pub turn: PieceColor,
     pub check: bool,
     pub checkmate: bool,
     pub stalemate: bool,
+    // the side to move ran out of time
+    pub timeout: bool,
+    // only set together with timeout, the other side can't mate anymore so it's a draw
+    pub insufficient_material: bool,
+}
+
+impl GameState {
+    pub fn new(board: Board) -> GameState {
+        let mut game_state = GameState {
+            board,
+            turn: board.turn,
+            check: false,
+            checkmate: false,
+            stalemate: false,
+            timeout: false,
+            insufficient_material: false,
+        };
+
+        game_state.update_status();
+        game_state
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.checkmate || self.stalemate || self.timeout
+    }
+
+    // Turn, check and mate all come straight from the board
+    pub fn update_status(&mut self) {
+        let no_moves = self.board.legal_moves().is_empty();
+
+        self.turn = self.board.turn;
+        self.check = self.board.in_check();
+        self.checkmate = self.check && no_moves;
+        self.stalemate = !self.check && no_moves;
+    }
+}
+
+#[derive(Reflect, Debug, Clone)]
+pub struct MoveRecord {
+    pub san: String,
+    pub color: PieceColor,
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub captured: Option<Piece>,
+}
+
+// Right click is green, shift red, alt blue and ctrl yellow (like on most chess sites)
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AnnotationColor {
+    #[default]
+    Green,
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl AnnotationColor {
+    pub fn from_keys(keys: &ButtonInput<KeyCode>) -> AnnotationColor {
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            AnnotationColor::Red
+        } else if keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+            AnnotationColor::Blue
+        } else if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            AnnotationColor::Yellow
+        } else {
+            AnnotationColor::Green
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            AnnotationColor::Green => Color::srgba(0.1, 0.65, 0.25, 0.8),
+            AnnotationColor::Red => Color::srgba(0.85, 0.15, 0.1, 0.8),
+            AnnotationColor::Blue => Color::srgba(0.1, 0.4, 0.85, 0.8),
+            AnnotationColor::Yellow => Color::srgba(0.95, 0.75, 0.1, 0.8),
+        }
+    }
+
+    // Letter used for it in the [%cal] / [%csl] PGN comments
+    pub fn letter(&self) -> char {
+        match self {
+            AnnotationColor::Green => 'G',
+            AnnotationColor::Red => 'R',
+            AnnotationColor::Blue => 'B',
+            AnnotationColor::Yellow => 'Y',
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arrow {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub color: AnnotationColor,
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarkedSquare {
+    pub tile: (u8, u8),
+    pub color: AnnotationColor,
+}
+
+// The arrows and circles drawn on one position
+#[derive(Reflect, Clone, Debug, Default)]
+pub struct PositionAnnotations {
+    pub arrows: Vec<Arrow>,
+    pub squares: Vec<MarkedSquare>,
+}
+
+impl PositionAnnotations {
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.squares.is_empty()
+    }
+
+    // Drawing the same arrow again takes it away, in another color it gets recolored
+    pub fn toggle_arrow(&mut self, arrow: Arrow) {
+        match self
+            .arrows
+            .iter()
+            .position(|a| a.from == arrow.from && a.to == arrow.to)
+        {
+            Some(i) if self.arrows[i].color == arrow.color => {
+                self.arrows.remove(i);
+            }
+            Some(i) => self.arrows[i].color = arrow.color,
+            None => self.arrows.push(arrow),
+        }
+    }
+
+    pub fn toggle_square(&mut self, square: MarkedSquare) {
+        match self.squares.iter().position(|s| s.tile == square.tile) {
+            Some(i) if self.squares[i].color == square.color => {
+                self.squares.remove(i);
+            }
+            Some(i) => self.squares[i].color = square.color,
+            None => self.squares.push(square),
+        }
+    }
+}
+
+// One move in the variation tree, nodes[0] is the starting position and has no move.
+// children[0] is the main continuation, the others are side variations
+#[derive(Reflect, Debug, Clone)]
+pub struct MoveNode {
+    pub record: Option<MoveRecord>,
+    pub board: Board,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// Every move that was played, as a tree so analysis can branch off anywhere.
+// line is the path through the tree that's shown (node ids from the start to the end of the line)
+// and moves / positions are that same line: positions[0] is the starting position,
+// positions[i + 1] is the board after moves[i]
+// cursor is the position the real pieces are in, viewing is an old one shown in the move list (None = the live game)
+// annotations are the right click arrows / circles, by node id
+// hints is how many positions the hint button was used in (once each, however far it went), it goes into the PGN
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct MoveHistory {
+    pub nodes: Vec<MoveNode>,
+    pub line: Vec<usize>,
+    pub moves: Vec<MoveRecord>,
+    pub positions: Vec<Board>,
+    pub cursor: usize,
+    pub viewing: Option<usize>,
+    pub annotations: HashMap<usize, PositionAnnotations>,
+    pub hints: u32,
+}
+
+impl MoveHistory {
+    // The tree for a new game, only the starting position is in it
+    pub fn start(&mut self, board: Board) {
+        self.nodes = vec![MoveNode {
+            record: None,
+            board,
+            parent: None,
+            children: Vec::new(),
+        }];
+        self.cursor = 0;
+        self.viewing = None;
+        self.set_line(0);
+    }
+
+    // The old position that's being looked at, None while showing the live game
+    pub fn viewed_position(&self) -> Option<&Board> {
+        self.viewing.and_then(|i| self.positions.get(i))
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.viewing.unwrap_or(self.cursor)
+    }
+
+    // Node of the shown position, 0 (the start) before anything was recorded
+    pub fn current_node(&self) -> usize {
+        self.line.get(self.current_index()).copied().unwrap_or(0)
+    }
+
+    // The move that led to the position the real pieces are in
+    pub fn last_move(&self) -> Option<&MoveRecord> {
+        self.cursor.checked_sub(1).and_then(|i| self.moves.get(i))
+    }
+
+    // Going to the live position means going back to the live game
+    pub fn view(&mut self, index: usize) {
+        let index = index.min(self.positions.len().saturating_sub(1));
+
+        self.viewing = if index == self.cursor {
+            None
+        } else {
+            Some(index)
+        };
+    }
+
+    // False for moves that were deleted from the tree
+    pub fn contains(&self, node: usize) -> bool {
+        node < self.nodes.len()
+            && self
+                .path(node)
+                .windows(2)
+                .all(|pair| self.nodes[pair[0]].children.contains(&pair[1]))
+    }
+
+    // Start of the tree up to the node
+    fn path(&self, node: usize) -> Vec<usize> {
+        let mut path = vec![node];
+
+        while let Some(parent) = path.last().and_then(|&n| self.nodes[n].parent) {
+            path.push(parent);
+        }
+
+        path.reverse();
+        path
+    }
+
+    // Shows the line through the node, after it the main continuation is followed to the end
+    fn set_line(&mut self, node: usize) {
+        let mut line = self.path(node);
+
+        while let Some(&next) = line.last().and_then(|&n| self.nodes[n].children.first()) {
+            line.push(next);
+        }
+
+        self.moves = line
+            .iter()
+            .filter_map(|&n| self.nodes[n].record.clone())
+            .collect();
+        self.positions = line.iter().map(|&n| self.nodes[n].board).collect();
+        self.line = line;
+    }
+
+    // Adds a move played from the live position. From an earlier position that makes a new
+    // variation, the same move as one that's already there just follows that one
+    pub fn play(&mut self, record: MoveRecord, board: Board) {
+        let parent = self.line[self.cursor];
+
+        let existing = self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].board == board);
+
+        let node = existing.unwrap_or_else(|| {
+            self.nodes.push(MoveNode {
+                record: Some(record),
+                board,
+                parent: Some(parent),
+                children: Vec::new(),
+            });
+
+            let node = self.nodes.len() - 1;
+            self.nodes[parent].children.push(node);
+            node
+        });
+
+        if self.line.get(self.cursor + 1) != Some(&node) {
+            self.set_line(node);
+        }
+
+        self.cursor += 1;
+        self.viewing = None;
+    }
+
+    // Clicked in the move list. The line switches over when the node is in another variation
+    pub fn select(&mut self, node: usize) {
+        let live = self.line.get(self.cursor).copied();
+
+        if !self.line.contains(&node) {
+            self.set_line(node);
+        }
+
+        let index = self.line.iter().position(|&n| n == node).unwrap_or(0);
+
+        match live.and_then(|live| self.line.iter().position(|&n| n == live)) {
+            Some(cursor) => {
+                self.cursor = cursor;
+                self.view(index);
+            }
+            // the live position isn't on this line anymore, so the shown one becomes live
+            None => {
+                self.cursor = index;
+                self.viewing = None;
+            }
+        }
+    }
+
+    // Moves the variation up until it's the main line everywhere above it
+    pub fn promote(&mut self, node: usize) {
+        let path = self.path(node);
+
+        for pair in path.windows(2) {
+            let (parent, child) = (pair[0], pair[1]);
+            let children = &mut self.nodes[parent].children;
+
+            children.retain(|&c| c != child);
+            children.insert(0, child);
+        }
+    }
+
+    // Takes the move and everything after it out of the tree.
+    // The nodes stay in the list but nothing points at them anymore
+    pub fn delete(&mut self, node: usize) {
+        let Some(parent) = self.nodes[node].parent else {
+            return;
+        };
+
+        self.nodes[parent].children.retain(|&c| c != node);
+
+        let mut removed = vec![node];
+
+        while let Some(n) = removed.pop() {
+            self.annotations.remove(&n);
+            removed.extend(self.nodes[n].children.iter().copied());
+        }
+
+        // the live or the viewed position could have been in what got deleted, then it's the parent
+        if let Some(index) = self.line.iter().position(|&n| n == node) {
+            let shown = self.current_index();
+
+            self.set_line(parent);
+            self.cursor = self.cursor.min(index - 1);
+            self.view(shown.min(index - 1));
+        }
+    }
+}
+
+// message is feedback shown in the game over dialog (e.g. where the PGN was saved)
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct GameOverDialog {
+    pub message: Option<String>,
 }
@@ -1,46 +1,301 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::camera::Viewport};
+use bevy_egui::{EguiContexts, egui};
 
 use crate::{
-    game::resources::*,
-    pieces::components::{ChessPiece, PieceColor, PieceType},
+    engine::{
+        board::{Move, square, tile},
+        notation::{move_to_san, parse_move},
+    },
+    evaluation::resources::{Hint, HintLevel, RequestHint},
+    game::{components::*, pgn::*, resources::*},
+    pieces::components::{BoardSquare, ChessPiece, PieceColor, PieceType},
+    settings::resources::SettingsWindow,
+    states::AppState,
+    theme::resources::Themes,
 };
 
 pub fn mouse_input(
     q_window: Query<&Window>,
     q_camera: Query<(&Camera, &GlobalTransform)>,
     buttons: Res<ButtonInput<MouseButton>>,
+    animations: Query<(), With<PieceAnimation>>,
+    history: Res<MoveHistory>,
+    layout: Res<BoardLayout>,
     mut current_tile: EventWriter<CurrentTile>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        let (camera, camera_transform) = q_camera.single().unwrap();
-        let window = q_window.single().unwrap();
-
-        if let Some(world_position) = window
-            .cursor_position()
-            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
-            .map(|ray| ray.origin.truncate())
-        {
-            let tile_coord = screen_coord_to_tile((world_position.x, world_position.y));
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // Input is locked until every piece has arrived where it's going
+    if !animations.is_empty() {
+        return;
+    }
+
+    // old positions from the move list are read-only
+    if history.viewing.is_some() {
+        return;
+    }
+
+    if let Some(world_position) = cursor_world_position(&q_window, &q_camera) {
+        let Some(tile_coord) = layout.world_to_tile(world_position) else {
+            println!("Clicked outside the board.");
+            return;
+        };
+
+        // Calls the event with the data (write = call event. current_tile is an event)
+        current_tile.write(CurrentTile((tile_coord.0, tile_coord.1)));
+    }
+}
+
+// Sprite follows the cursor while the mouse button is held down
+pub fn drag_piece(
+    q_window: Query<&Window>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    dragged: Res<DraggedPiece>,
+    mut query: Query<&mut Transform, With<ChessPiece>>,
+) {
+    let Some(entity) = dragged.entity else {
+        return;
+    };
+
+    if !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+
+    if let (Some(world_position), Ok(mut transform)) = (
+        cursor_world_position(&q_window, &q_camera),
+        query.get_mut(entity),
+    ) {
+        transform.translation.x = world_position.x;
+        transform.translation.y = world_position.y;
+        transform.translation.z = DRAG_Z;
+    }
+}
+
+// Letting go of a dragged piece on another tile is the same as clicking that tile.
+// The sprite stays where it was dropped, move_piece slides it onto the new tile
+// if the move was legal and settle_dropped_piece sends it back otherwise
+pub fn drop_piece(
+    q_window: Query<&Window>,
+    q_camera: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut dragged: ResMut<DraggedPiece>,
+    layout: Res<BoardLayout>,
+    mut move_piece_events: EventWriter<MovePiece>,
+) {
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+
+    let Some(entity) = dragged.entity else {
+        return;
+    };
+
+    dragged.dropped = true;
+
+    if let Some(world_position) = cursor_world_position(&q_window, &q_camera) {
+        // dropped outside the board or back on the same tile, so nothing to do
+        let Some(tile) = layout
+            .world_to_tile(world_position)
+            .filter(|tile| *tile != dragged.from)
+        else {
+            return;
+        };
+
+        move_piece_events.write(MovePiece {
+            piece: entity,
+            from: dragged.from,
+            to: tile,
+            promotion: None,
+            computer: false,
+        });
+    }
+}
+
+// Runs after move_piece. If the dropped piece didn't get an animation
+// the move didn't go through, so it slides back to where it was picked up
+pub fn settle_dropped_piece(
+    mut dragged: ResMut<DraggedPiece>,
+    query: Query<&Transform, (With<ChessPiece>, Without<PieceAnimation>)>,
+    layout: Res<BoardLayout>,
+    animation_settings: Res<AnimationSettings>,
+    mut commands: Commands,
+) {
+    if !dragged.dropped {
+        return;
+    }
+
+    dragged.dropped = false;
+
+    let Some(entity) = dragged.entity.take() else {
+        return;
+    };
 
-            if (tile_coord.0, tile_coord.1) == (0, 0) {
-                println!("Clicked outside the board.");
+    if let Ok(transform) = query.get(entity) {
+        slide_to_tile(
+            &mut commands,
+            entity,
+            transform.translation,
+            dragged.from,
+            &layout,
+            &animation_settings,
+        );
+    }
+}
+
+// Run condition for mouse_input, only computer vs computer games can't be touched.
+// While the computer is thinking the human can still queue a premove
+pub fn human_at_board(setup: Res<GameSetup>, state: Res<State<AppState>>) -> bool {
+    *state.get() != AppState::Playing
+        || setup.white == PlayerKind::Human
+        || setup.black == PlayerKind::Human
+}
+
+// In a game against the computer its pieces are only moved by the AI
+fn computers_turn(turn: PieceColor, setup: &GameSetup, state: &State<AppState>) -> bool {
+    *state.get() == AppState::Playing && setup.player(turn) != PlayerKind::Human
+}
+
+// The side that's waiting can queue a move when it's human and the other side is the computer
+fn can_premove(color: PieceColor, setup: &GameSetup, state: &State<AppState>) -> bool {
+    *state.get() == AppState::Playing
+        && setup.player(color) == PlayerKind::Human
+        && setup.player(color.opposite()) == PlayerKind::Computer
+}
+
+// Plays the queued premove once it's our turn, or throws it away if it isn't legal anymore
+pub fn play_premove(
+    mut selections: ResMut<Selections>,
+    game_state: Res<GameState>,
+    query: Query<&ChessPiece>,
+    mut move_piece_events: EventWriter<MovePiece>,
+) {
+    let Some(Premove { piece, from, to }) = selections.premove else {
+        return;
+    };
+
+    // taken by the computer, whatever is on the tile now isn't ours to move
+    let Some(chess_piece) = query.get(piece).ok().filter(|p| p.position == from) else {
+        println!("Premove piece on {:?} is gone", from);
+        selections.premove = None;
+        return;
+    };
+
+    // still the computer's turn
+    if chess_piece.color != game_state.turn {
+        return;
+    }
+
+    selections.premove = None;
+
+    if game_state
+        .board
+        .find_move(square(from), square(to), None)
+        .is_none()
+    {
+        println!("Premove from {:?} to {:?} isn't legal anymore", from, to);
+        return;
+    }
+
+    move_piece_events.write(MovePiece {
+        piece,
+        from,
+        to,
+        promotion: None,
+        computer: false,
+    });
+}
+
+// Keys like F and C are letters that show up in moves too
+pub fn not_typing(move_input: Res<MoveInput>) -> bool {
+    !move_input.typing
+}
+
+// Typed moves go through MovePiece just like clicked ones do,
+// anything that's wrong with them gets shown under the text field
+#[allow(clippy::too_many_arguments)]
+pub fn submit_typed_move(
+    mut move_input: ResMut<MoveInput>,
+    game_state: Res<GameState>,
+    history: Res<MoveHistory>,
+    setup: Res<GameSetup>,
+    state: Res<State<AppState>>,
+    q_pieces: Query<(Entity, &ChessPiece)>,
+    mut move_events: EventWriter<MovePiece>,
+    mut illegal_events: EventWriter<IllegalMove>,
+) {
+    if move_input.submitted.is_none() {
+        return;
+    }
+
+    let text = move_input.submitted.take().unwrap_or_default();
+
+    let result = if *state.get() == AppState::GameOver || game_state.is_over() {
+        Err("The game is over".to_string())
+    } else if history.viewing.is_some() {
+        Err("Go back to the last position first".to_string())
+    } else if computers_turn(game_state.turn, &setup, &state) {
+        Err("It's not your move".to_string())
+    } else {
+        parse_move(&game_state.board, &text)
+    };
+
+    match result {
+        Ok(mv) => {
+            let (from, to) = (tile(mv.from), tile(mv.to));
+
+            let Some((piece, _)) = q_pieces.iter().find(|(_, p)| p.position == from) else {
+                println!("No piece entity on {:?} for {}", from, text);
                 return;
-            }
+            };
 
-            // Calls the event with the data (write = call event. current_tile is an event)
-            current_tile.write(CurrentTile((tile_coord.0, tile_coord.1)));
+            println!("Typed move {} ({:?} to {:?})", text, from, to);
+
+            move_events.write(MovePiece {
+                piece,
+                from,
+                to,
+                promotion: mv.promotion,
+                computer: false,
+            });
+
+            move_input.text.clear();
+            move_input.error = None;
+        }
+        Err(error) => {
+            move_input.error = Some(error);
+            illegal_events.write(IllegalMove);
         }
     }
 }
 
+pub fn cursor_world_position(
+    q_window: &Query<&Window>,
+    q_camera: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = q_camera.single().unwrap();
+    let window = q_window.single().unwrap();
+
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok())
+        .map(|ray| ray.origin.truncate())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn tile_clicked(
     mut events: EventReader<CurrentTile>,
     mut selections: ResMut<Selections>,
     query: Query<(Entity, &ChessPiece)>,
     mut move_piece_events: EventWriter<MovePiece>,
+    mut dragged: ResMut<DraggedPiece>,
     game_state: Res<GameState>,
+    setup: Res<GameSetup>,
+    state: Res<State<AppState>>,
 ) {
-    if game_state.checkmate || game_state.stalemate {
+    if game_state.is_over() {
         println!("Game over! Cannot make any moves.");
         return;
     }
@@ -52,7 +307,7 @@ pub fn tile_clicked(
         if selections.selected_piece.is_none() && selections.second_selected_piece.is_none() {
             if let Some((entity, _piece)) = query.iter().find(|(_, piece)| piece.position == *tile)
             {
-                if game_state.turn != _piece.color {
+                if game_state.turn != _piece.color && !can_premove(_piece.color, &setup, &state) {
                     println!(
                         "It's not your turn! Current turn: {:?}, piece color: {:?}",
                         game_state.turn, _piece.color
@@ -60,23 +315,46 @@ pub fn tile_clicked(
                     continue;
                 }
 
+                if computers_turn(_piece.color, &setup, &state) {
+                    println!("The computer is playing {:?}", _piece.color);
+                    continue;
+                }
+
                 println!("Selected piece: {:?}", entity);
                 selections.selected_piece = Some(entity);
+
+                // the same press can turn into a drag if the mouse moves
+                dragged.entity = Some(entity);
+                dragged.from = _piece.position;
             } else {
                 println!("No piece at tile: {:?}", tile);
+
+                // clicking an empty tile takes a premove back
+                if selections.premove.is_some() {
+                    selections.premove = None;
+                }
             }
         }
         // tile gets selected
         else if let Some(selected_entity) = selections.selected_piece {
             if let Ok(piece) = query.get(selected_entity) {
-                // If the clicked tile has a piece of the same color, change selection
+                // If the clicked tile has a piece of the same color, change selection.
+                // The king clicking its own rook is castling though
+                let castles = game_state
+                    .board
+                    .find_move(square(piece.1.position), square(*tile), None)
+                    .is_some();
+
                 if let Some((entity, clicked_piece)) =
                     query.iter().find(|(_, p)| p.position == *tile)
                 {
-                    if clicked_piece.color == piece.1.color {
+                    if clicked_piece.color == piece.1.color && !castles {
                         selections.selected_piece = Some(entity);
                         selections.second_selected_piece = None;
                         selections.second_selected_tile = None;
+
+                        dragged.entity = Some(entity);
+                        dragged.from = clicked_piece.position;
                         continue;
                     }
                 }
@@ -85,825 +363,1169 @@ pub fn tile_clicked(
                     piece: selected_entity,
                     from: piece.1.position,
                     to: *tile,
+                    promotion: None,
+                    computer: false,
                 });
             }
         }
     }
 }
 
+// Everything about whether a move is legal comes from the board in GameState,
+// this just finds the matching move and makes the sprites follow it
+#[allow(clippy::too_many_arguments)]
 pub fn move_piece(
     mut events: EventReader<MovePiece>,
     mut event_move_made: EventWriter<MoveMade>,
     mut castle_events: EventWriter<Castle>,
+    mut illegal_events: EventWriter<IllegalMove>,
     mut selections: ResMut<Selections>,
-    mut query_set: ParamSet<(
-        Query<&mut ChessPiece>,
-        Query<(Entity, &ChessPiece, &mut Transform)>,
-    )>,
+    mut game_state: ResMut<GameState>,
+    mut query: Query<(Entity, &mut ChessPiece, &Transform)>,
+    layout: Res<BoardLayout>,
+    animation_settings: Res<AnimationSettings>,
+    setup: Res<GameSetup>,
+    state: Res<State<AppState>>,
     mut commands: Commands,
 ) {
-    for MovePiece { piece, from, to } in events.read() {
-        // checks whether the second selection is a piece or a tile
-        // second selection is a piece
-        let pieces: Vec<ChessPiece> = query_set
-            .p1()
-            .iter()
-            .map(|(_, p, _)| p.clone())
-            .collect::<Vec<ChessPiece>>();
+    for MovePiece {
+        piece,
+        from,
+        to,
+        promotion,
+        computer,
+    } in events.read()
+    {
+        let board = game_state.board;
 
-        if let Some((other_entity, other_piece_chess_piece, _)) =
-            query_set.p1().iter().find(|(_, p, _)| p.position == *to)
+        // pieces of the side that's waiting can only be picked up for a premove,
+        // the move is checked once it's actually played
+        if query
+            .get(*piece)
+            .is_ok_and(|(_, p, _)| p.color != board.turn && can_premove(p.color, &setup, &state))
         {
-            // Extract the data needed from other_piece_chess_piece before the mutable borrow
-            let other_piece_position = other_piece_chess_piece.position;
-            let other_piece_color = other_piece_chess_piece.color;
-            let other_piece_type = other_piece_chess_piece.piece;
-
-            if let Ok(mut moving_piece) = query_set.p0().get_mut(*piece) {
-                selections.second_selected_piece = Some(other_entity);
-
-                let pieces_refs: Vec<&ChessPiece> = pieces.iter().collect();
-                if can_move_to_tile(&moving_piece, *to, &pieces_refs, true).0 {
-                    if is_king_in_check(&moving_piece.color, &pieces.iter().collect::<Vec<_>>()) {
-                        let mut hypothetical_pieces = pieces.clone();
-
-                        if let Some(p) = hypothetical_pieces.iter_mut().find(|p| {
-                            p.position == moving_piece.position
-                                && p.color == moving_piece.color
-                                && p.piece == moving_piece.piece
-                        }) {
-                            p.position = *to;
-                        }
-
-                        if let Some(_p) = hypothetical_pieces.iter_mut().find(|p| {
-                            p.position == other_piece_position
-                                && p.color == other_piece_color
-                                && p.piece == other_piece_type
-                        }) {
-                            hypothetical_pieces.retain(|p| {
-                                !(p.position == other_piece_position
-                                    && p.color == other_piece_color
-                                    && p.piece == other_piece_type)
-                            });
-                        }
-
-                        //println!("Pieces: {:?}", hypothetical_pieces);
-
-                        let king_in_check = if is_king_in_check(
-                            &moving_piece.color,
-                            &hypothetical_pieces.iter().collect::<Vec<_>>(),
-                        ) {
-                            println!(
-                                "Move puts king in check, cannot move {:?} to {:?}",
-                                moving_piece, to
-                            );
-
-                            true
-                        } else {
-                            false
-                        };
-
-                        if king_in_check {
-                            return;
-                        }
-
-                        moving_piece.position = *to;
-
-                        // Also update the transform position
-                        if let Ok((_, _, mut transform)) = query_set.p1().get_mut(*piece) {
-                            let (x, y) = tile_to_screen_coord(*to);
-                            transform.translation.x = x;
-                            transform.translation.y = y;
-                        }
-
-                        selections.selected_piece = None;
-                        selections.second_selected_piece = None;
-                        selections.second_selected_tile = None;
+            println!("Premove from {:?} to {:?}", from, to);
 
-                        commands.entity(other_entity).despawn();
+            selections.premove = Some(Premove {
+                piece: *piece,
+                from: *from,
+                to: *to,
+            });
+            selections.selected_piece = None;
+            selections.second_selected_piece = None;
+            selections.second_selected_tile = None;
+            continue;
+        }
 
-                        event_move_made.write(MoveMade {});
-                    } else {
-                        let pos_before_moved = moving_piece.position;
+        if !*computer && computers_turn(board.turn, &setup, &state) {
+            println!("It's the computer's move, not yours");
+            continue;
+        }
 
-                        println!("Attacking piece {:?} from {:?} to {:?}", piece, from, to);
-                        moving_piece.position = *to;
+        // clicking only gives the tiles, so pawns turn into a queen unless something else was asked for.
+        // The king can castle by going to its target tile or onto its own rook
+        let Some(mv) = board.find_move(square(*from), square(*to), *promotion) else {
+            println!("Invalid move from {:?} to {:?}", from, to);
+            illegal_events.write(IllegalMove);
 
-                        let mut hypothetical_pieces = pieces.clone();
+            selections.second_selected_piece = None;
+            selections.second_selected_tile = None;
+            continue;
+        };
 
-                        if let Some(p) = hypothetical_pieces.iter_mut().find(|p| {
-                            p.position == pos_before_moved
-                                && p.color == moving_piece.color
-                                && p.piece == moving_piece.piece
-                        }) {
-                            p.position = *to;
-                        }
+        println!("Moving piece {:?} from {:?} to {:?}", piece, from, to);
 
-                        // Remove the captured piece from hypothetical_pieces
-                        hypothetical_pieces.retain(|p| {
-                            !(p.position == other_piece_position
-                                && p.color == other_piece_color
-                                && p.piece == other_piece_type)
-                        });
+        // en passant takes a pawn that isn't on the target tile
+        let captured_tile = tile(board.captured_square(mv));
+        let king_tile = tile(board.destination(mv));
 
-                        let is_king_in_check = is_king_in_check(
-                            &moving_piece.color,
-                            &hypothetical_pieces.iter().collect::<Vec<_>>(),
-                        );
-
-                        println!("Is king in check after move? {}", is_king_in_check);
-
-                        if is_king_in_check {
-                            // If the king is in check after the move, revert the move
-                            println!("Move puts king in check, reverting.");
-                            moving_piece.position = pos_before_moved;
-
-                            // Optionally, you could notify the player or handle this case differently
-                            selections.selected_piece = None;
-                            selections.second_selected_piece = None;
-                            selections.second_selected_tile = None;
-                            continue;
-                        }
-
-                        // Also update the transform position
-                        if let Ok((_, _, mut transform)) = query_set.p1().get_mut(*piece) {
-                            let (x, y) = tile_to_screen_coord(*to);
-                            transform.translation.x = x;
-                            transform.translation.y = y;
-                        }
-
-                        selections.selected_piece = None;
-                        selections.second_selected_piece = None;
-                        selections.second_selected_tile = None;
+        // Captured piece stops being a ChessPiece right away but fades out before it's despawned.
+        // Castling ends on our own rook, that one isn't taken
+        if let Some((captured_entity, _, transform)) = query.iter().find(|(entity, p, _)| {
+            board.captured_piece(mv).is_some() && *entity != *piece && p.position == captured_tile
+        }) {
+            let start_scale = transform.scale;
+            commands
+                .entity(captured_entity)
+                .remove::<ChessPiece>()
+                .insert(CaptureAnimation {
+                    start_scale,
+                    timer: Timer::from_seconds(
+                        animation_settings.capture_duration,
+                        TimerMode::Once,
+                    ),
+                });
+        }
 
-                        commands.entity(other_entity).despawn();
+        if let Ok((_, mut moving_piece, transform)) = query.get_mut(*piece) {
+            moving_piece.position = king_tile;
 
-                        event_move_made.write(MoveMade {});
-                    }
-                }
+            if let Some(kind) = mv.promotion {
+                moving_piece.promote(kind);
             }
+
+            // Slide the sprite over instead of teleporting it
+            slide_to_tile(
+                &mut commands,
+                *piece,
+                transform.translation,
+                king_tile,
+                &layout,
+                &animation_settings,
+            );
         }
-        // second selection is a tile
-        else {
-            selections.second_selected_tile = Some(*to);
-
-            // Use the first query (mutable) to try moving the piece
-            if let Ok(mut moving_piece) = query_set.p0().get_mut(*piece) {
-                let (can_move, is_castling) = can_move_to_tile(
-                    &moving_piece,
-                    *to,
-                    &pieces.iter().collect::<Vec<_>>(),
-                    false,
-                );
-                if can_move {
-                    let pos_before_moved = moving_piece.position;
 
-                    println!("Moving piece {:?} from {:?} to {:?}", piece, from, to);
-                    moving_piece.position = *to;
+        if board.is_castling(mv) {
+            let (_, rook_to) = board.castling_targets(mv);
 
-                    let mut hypothetical_pieces = pieces.clone();
+            castle_events.write(Castle {
+                color: board.turn,
+                rook_from: tile(mv.to),
+                rook_to: tile(rook_to),
+            });
+        }
 
-                    if let Some(p) = hypothetical_pieces.iter_mut().find(|p| {
-                        p.position == pos_before_moved
-                            && p.color == moving_piece.color
-                            && p.piece == moving_piece.piece
-                    }) {
-                        p.position = *to;
-                    }
+        game_state.board = board.make_move(mv);
 
-                    let is_king_in_check = is_king_in_check(
-                        &moving_piece.color,
-                        &hypothetical_pieces.iter().collect::<Vec<_>>(),
-                    );
+        selections.selected_piece = None;
+        selections.second_selected_piece = None;
+        selections.second_selected_tile = None;
 
-                    println!("Is king in check after move? {}", is_king_in_check);
+        // castling is recorded as the king going onto its rook, like the board has it
+        event_move_made.write(MoveMade {
+            from: *from,
+            to: tile(mv.to),
+            promotion: mv.promotion,
+        });
+    }
+}
 
-                    if is_king_in_check {
-                        // If the king is in check after the move, revert the move
-                        println!("Move puts king in check, reverting.");
-                        moving_piece.position = pos_before_moved;
+pub fn move_made(mut event_move_made: EventReader<MoveMade>, mut game_state: ResMut<GameState>) {
+    for MoveMade { .. } in event_move_made.read() {
+        println!("Move made event triggered.");
 
-                        // Optionally, you could notify the player or handle this case differently
-                        selections.selected_piece = None;
-                        selections.second_selected_piece = None;
-                        selections.second_selected_tile = None;
-                        continue;
+        game_state.update_status();
+    }
+}
+
+pub fn castle(
+    mut castle_event: EventReader<Castle>,
+    mut query: Query<(Entity, &mut ChessPiece, &Transform)>,
+    layout: Res<BoardLayout>,
+    animation_settings: Res<AnimationSettings>,
+    mut commands: Commands,
+) {
+    for Castle {
+        color,
+        rook_from,
+        rook_to,
+    } in castle_event.read()
+    {
+        // in chess960 the king can already be standing on the rook's tile, so look for the rook itself
+        let rook = query.iter_mut().find(|(_, piece, _)| {
+            piece.position == *rook_from && piece.color == *color && piece.piece == PieceType::Rook
+        });
+
+        if let Some((rook_entity, mut rook, transform)) = rook {
+            rook.position = *rook_to;
+
+            // Same frame as the king's animation so both slide together
+            slide_to_tile(
+                &mut commands,
+                rook_entity,
+                transform.translation,
+                *rook_to,
+                &layout,
+                &animation_settings,
+            );
+        }
+    }
+}
+
+// Side panel with the game info on top and the move list below it
+#[allow(clippy::too_many_arguments)]
+pub fn update_ui(
+    mut contexts: EguiContexts,
+    game_state: Res<GameState>,
+    mut history: ResMut<MoveHistory>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut move_input: ResMut<MoveInput>,
+    mut settings_window: ResMut<SettingsWindow>,
+    hint: Res<Hint>,
+    mut hint_requests: EventWriter<RequestHint>,
+    setup: Res<GameSetup>,
+    mut saved_message: Local<Option<String>>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    egui::SidePanel::right("move_list")
+        .resizable(false)
+        .exact_width(SIDE_PANEL_WIDTH)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Turn: {:?}\nCheck: {:?}\nCheckmate: {:?}\nStalemate: {:?}\nTimeout: {:?}",
+                game_state.turn,
+                game_state.check,
+                game_state.checkmate,
+                game_state.stalemate,
+                game_state.timeout
+            ));
+
+            ui.separator();
+            ui.label("Type a move (Nf3, e2e4, O-O)");
+
+            let response = ui.text_edit_singleline(&mut move_input.text);
+
+            if response.changed() {
+                move_input.error = None;
+            }
+
+            // enter takes the focus away, so it's given back right away to type the next move
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let text = move_input.text.trim().to_string();
+
+                if !text.is_empty() {
+                    move_input.submitted = Some(text);
+                }
+
+                response.request_focus();
+            }
+
+            if let Some(error) = &move_input.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+
+            // the first press only shows which piece to move, the second one the whole move
+            ui.horizontal(|ui| {
+                let text = match hint.level {
+                    HintLevel::Piece => "Show the move (H)",
+                    _ => "Hint (H)",
+                };
+
+                if ui
+                    .add_enabled(!hint.searching, egui::Button::new(text))
+                    .clicked()
+                {
+                    hint_requests.write(RequestHint);
+                }
+
+                if hint.searching {
+                    ui.spinner();
+                } else if history.hints > 0 {
+                    ui.label(format!("{} used", history.hints));
+                }
+            });
+
+            ui.separator();
+            ui.heading("Moves");
+
+            if history.viewing.is_some() {
+                ui.label("Viewing an old position (use the arrow keys or click the last move to go back)");
+            }
+
+            // in analysis moves can be promoted or deleted with a right click
+            let editable = *state.get() == AppState::Analysis;
+            let current = history.current_node();
+            let mut action = None;
+
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .max_height(ui.available_height() - 80.0)
+                .show(ui, |ui| {
+                    if let Some(&first) = history.nodes.first().and_then(|root| root.children.first()) {
+                        move_tree_ui(ui, &history, first, current, editable, &mut action);
                     }
+                });
 
-                    if is_castling {
-                        castle_events.write(Castle {
-                            king_pos: moving_piece.position,
-                        });
+            match action {
+                Some(TreeAction::Select(node)) => history.select(node),
+                Some(TreeAction::Promote(node)) => history.promote(node),
+                Some(TreeAction::Delete(node)) => history.delete(node),
+                None => {}
+            }
+
+            ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
+                if ui.button("Main menu").clicked() {
+                    next_state.set(AppState::MainMenu);
+                }
+
+                // the whole tree with all the variations, the game over dialog saves finished games
+                if editable {
+                    if let Some(message) = saved_message.as_ref() {
+                        ui.label(message);
                     }
 
-                    // Also update the transform position
-                    if let Ok((_, _, mut transform)) = query_set.p1().get_mut(*piece) {
-                        let (x, y) = tile_to_screen_coord(*to);
-                        transform.translation.x = x;
-                        transform.translation.y = y;
+                    if ui.button("Save PGN").clicked() {
+                        *saved_message = Some(match save_pgn(&game_to_pgn(&history, &setup, "*")) {
+                            Ok(path) => format!("Saved to {}", path.display()),
+                            Err(e) => format!("Couldn't save the game: {}", e),
+                        });
                     }
+                }
 
-                    selections.selected_piece = None;
-                    selections.second_selected_piece = None;
-                    selections.second_selected_tile = None;
+                if ui.button("Settings").clicked() {
+                    settings_window.open = true;
+                }
+            });
+        });
+}
+
+// What was clicked in the move tree, it's drawn from a borrowed history so this gets applied after
+enum TreeAction {
+    Select(usize),
+    Promote(usize),
+    Delete(usize),
+}
 
-                    event_move_made.write(MoveMade {});
-                } else {
-                    selections.second_selected_piece = None;
-                    selections.second_selected_tile = None;
+// A line of the move tree starting at the node. Moves run on in rows until one has side variations,
+// those go indented below it and then the line carries on
+fn move_tree_ui(
+    ui: &mut egui::Ui,
+    history: &MoveHistory,
+    first: usize,
+    current: usize,
+    editable: bool,
+    action: &mut Option<TreeAction>,
+) {
+    let mut start = Some(first);
+    let mut show_number = true;
+
+    while let Some(node) = start {
+        let (variations, next) = ui
+            .horizontal_wrapped(|ui| {
+                let mut node = node;
+
+                loop {
+                    move_label(ui, history, node, show_number, current, editable, action);
+                    show_number = false;
+
+                    let next = history.nodes[node].children.first().copied();
+                    let siblings = history.nodes[node]
+                        .parent
+                        .map_or(&[][..], |parent| &history.nodes[parent].children[..]);
+
+                    // only the main move shows the variations next to it
+                    if siblings.len() > 1 && siblings[0] == node {
+                        return (&siblings[1..], next);
+                    }
 
-                    println!("Invalid move for piece: {:?}", moving_piece);
+                    match next {
+                        Some(next) => node = next,
+                        None => return (&[][..], None),
+                    }
                 }
-            }
+            })
+            .inner;
+
+        for &variation in variations {
+            ui.indent(variation, |ui| {
+                move_tree_ui(ui, history, variation, current, editable, action);
+            });
         }
+
+        // coming back from the variations black's move needs its number again
+        show_number = true;
+        start = next;
     }
 }
 
-pub fn move_made(
-    mut event_move_made: EventReader<MoveMade>,
-    mut game_state: ResMut<GameState>,
-    query: Query<(Entity, &ChessPiece)>,
+fn move_label(
+    ui: &mut egui::Ui,
+    history: &MoveHistory,
+    node: usize,
+    show_number: bool,
+    current: usize,
+    editable: bool,
+    action: &mut Option<TreeAction>,
 ) {
-    for MoveMade {} in event_move_made.read() {
-        println!("Move made event triggered.");
+    let MoveNode {
+        record: Some(record),
+        parent: Some(parent),
+        ..
+    } = &history.nodes[node]
+    else {
+        return;
+    };
 
-        game_state.turn = match game_state.turn {
-            PieceColor::White => PieceColor::Black,
-            PieceColor::Black => PieceColor::White,
-        };
+    let number = history.nodes[*parent].board.fullmove_number;
+
+    if record.color == PieceColor::White {
+        ui.label(format!("{}.", number));
+    } else if show_number {
+        ui.label(format!("{}...", number));
+    }
 
-        let pieces: Vec<&ChessPiece> = query.iter().map(|(_, p)| p).collect();
+    let response = ui.selectable_label(node == current, &record.san);
 
-        if is_king_in_check(&game_state.turn, &pieces) {
-            game_state.check = true;
+    if response.clicked() {
+        *action = Some(TreeAction::Select(node));
+    }
 
-            if has_free_squares(query, &game_state.turn)
-                || can_capture_attacker(query, &game_state.turn)
-            {
-                game_state.checkmate = false;
-            } else if !can_capture_attacker(query, &game_state.turn) {
-                game_state.checkmate = true;
+    if editable {
+        response.context_menu(|ui| {
+            if ui.button("Make main line").clicked() {
+                *action = Some(TreeAction::Promote(node));
+                ui.close_menu();
             }
-        } else {
-            game_state.check = false;
-
-            let only_king_exists = pieces
-                .iter()
-                .filter(|p| p.color == game_state.turn && p.piece != PieceType::King)
-                .count()
-                == 0;
-
-            if has_free_squares(query, &game_state.turn) {
-                game_state.stalemate = false;
-            } else if only_king_exists {
-                game_state.stalemate = true;
+
+            if ui.button("Delete from here").clicked() {
+                *action = Some(TreeAction::Delete(node));
+                ui.close_menu();
             }
-        }
+        });
     }
 }
 
-fn can_capture_attacker(pieces_query: Query<(Entity, &ChessPiece)>, color: &PieceColor) -> bool {
-    let pieces: Vec<ChessPiece> = pieces_query.iter().map(|(_, p)| p.clone()).collect();
+// Any text field that has the keyboard (typed moves, the settings, the inspector)
+// turns the single key shortcuts off
+pub fn track_typing(mut contexts: EguiContexts, mut move_input: ResMut<MoveInput>) {
+    let typing = contexts.ctx_mut().wants_keyboard_input();
 
-    // Get all enemy pieces
-    let enemy_pieces: Vec<ChessPiece> = pieces
-        .iter()
-        .filter(|p| p.color != *color)
-        .cloned()
-        .collect();
+    if move_input.typing != typing {
+        move_input.typing = typing;
+    }
+}
+
+// Left / right arrow go through the positions of the move list
+pub fn move_list_keys(keys: Res<ButtonInput<KeyCode>>, mut history: ResMut<MoveHistory>) {
+    let current = history.current_index();
+
+    if keys.just_pressed(KeyCode::ArrowLeft) && current > 0 {
+        history.view(current - 1);
+    }
+
+    if keys.just_pressed(KeyCode::ArrowRight) {
+        history.view(current + 1);
+    }
+}
 
-    // Get all friendly pieces
-    let friendly_pieces: Vec<ChessPiece> = pieces
-        .iter()
-        .filter(|p| p.color == *color)
-        .cloned()
-        .collect();
+// Adds the move (in SAN) and the board after it to the history
+pub fn record_move(
+    mut event_move_made: EventReader<MoveMade>,
+    mut history: ResMut<MoveHistory>,
+    game_state: Res<GameState>,
+) {
+    // the first time this runs nothing has moved yet so that's the starting position
+    if history.nodes.is_empty() {
+        history.start(game_state.board);
+    }
 
-    // Find the king
-    let king = match pieces
-        .iter()
-        .find(|p| p.color == *color && p.piece == PieceType::King)
+    for MoveMade {
+        from,
+        to,
+        promotion,
+    } in event_move_made.read()
     {
-        Some(k) => k,
-        None => return false,
-    };
+        let before = history
+            .positions
+            .get(history.cursor)
+            .copied()
+            .unwrap_or_default();
+        let mv = Move {
+            from: square(*from),
+            to: square(*to),
+            promotion: *promotion,
+        };
 
-    // Identify the piece(s) attacking the king
-    let attackers: Vec<&ChessPiece> = enemy_pieces
-        .iter()
-        .filter(|enemy| can_move_to_tile(enemy, king.position, &pieces.iter().collect(), true).0)
-        .collect();
+        let san = move_to_san(&before, mv);
+
+        println!("Recorded move: {}", san);
+
+        // a new move always jumps back to the live game
+        history.play(
+            MoveRecord {
+                san,
+                color: before.turn,
+                from: *from,
+                to: *to,
+                captured: before.captured_piece(mv),
+            },
+            before.make_move(mv),
+        );
+    }
+}
 
-    if attackers.is_empty() {
-        return false; // Not in check
+// In analysis there's nothing read-only about old positions, whatever gets shown
+// in the move list is put on the real board so it can be played from
+pub fn follow_viewed_position(
+    mut history: ResMut<MoveHistory>,
+    game_state: Res<GameState>,
+    mut jump: EventWriter<JumpToPosition>,
+) {
+    if !history.is_changed() {
+        return;
     }
 
-    // If multiple attackers, can't capture all in one move
-    if attackers.len() > 1 {
-        return false;
+    let current = history.current_index();
+
+    let Some(board) = history.positions.get(current).copied() else {
+        return;
+    };
+
+    if history.viewing.is_some() {
+        history.cursor = current;
+        history.viewing = None;
     }
 
-    let attacker = attackers[0];
+    if board != game_state.board {
+        jump.write(JumpToPosition { board });
+    }
+}
 
-    // Try capturing the attacker with any friendly piece (except the king)
-    for piece in friendly_pieces
-        .iter()
-        .filter(|p| p.piece != PieceType::King)
+// The game state follows a jump, the pieces get set up again by the pieces plugin
+pub fn jump_to_position(
+    mut events: EventReader<JumpToPosition>,
+    mut game_state: ResMut<GameState>,
+    mut selections: ResMut<Selections>,
+    mut dragged: ResMut<DraggedPiece>,
+) {
+    for JumpToPosition { board } in events.read() {
+        *game_state = GameState::new(*board);
+
+        selections.selected_piece = None;
+        selections.second_selected_piece = None;
+        selections.second_selected_tile = None;
+        selections.premove = None;
+
+        dragged.entity = None;
+        dragged.dropped = false;
+    }
+}
+
+// Swaps the real pieces for preview sprites while an old position is viewed
+#[allow(clippy::too_many_arguments)]
+pub fn show_viewed_position(
+    history: Res<MoveHistory>,
+    orientation: Res<BoardOrientation>,
+    layout: Res<BoardLayout>,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+    mut q_pieces: Query<&mut Visibility, With<ChessPiece>>,
+    q_previews: Query<Entity, With<PreviewPiece>>,
+    mut commands: Commands,
+) {
+    // previews are simply spawned again when the board gets flipped, resized or themed
+    if !history.is_changed()
+        && !orientation.is_changed()
+        && !layout.is_changed()
+        && !themes.is_changed()
     {
-        if can_move_to_tile(piece, attacker.position, &pieces.iter().collect(), false).0 {
-            // Simulate capture
-            let mut hypothetical_pieces = pieces.clone();
+        return;
+    }
 
-            if let Some(p) = hypothetical_pieces
-                .iter_mut()
-                .find(|p| p.position == piece.position)
-            {
-                p.position = attacker.position; // move to attacker's tile
-            }
+    for entity in q_previews.iter() {
+        commands.entity(entity).despawn();
+    }
 
-            // Remove the attacker
-            hypothetical_pieces.retain(|p| p.position != attacker.position || p.color == *color);
+    let viewed = history.viewed_position();
 
-            if !is_king_in_check(color, &hypothetical_pieces.iter().collect::<Vec<_>>()) {
-                println!("Capture attacker at {:?}", attacker.position);
-                return true;
-            }
-        }
+    for mut visibility in q_pieces.iter_mut() {
+        *visibility = if viewed.is_some() {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
     }
 
-    false
-}
+    let Some(board) = viewed else {
+        return;
+    };
 
-fn has_free_squares(pieces_query: Query<(Entity, &ChessPiece)>, color: &PieceColor) -> bool {
-    // Find all pieces of the given color
-    let pieces: Vec<ChessPiece> = pieces_query.iter().map(|(_, p)| p.clone()).collect();
+    let rotation = orientation.rotation();
+
+    for (square, piece) in board.pieces() {
+        commands.spawn((
+            Sprite::from_image(
+                asset_server.load(themes.current().piece_image(piece.kind, piece.color)),
+            ),
+            Transform {
+                translation: layout.tile_to_world(tile(square)).extend(0.0),
+                rotation,
+                scale: Vec3::splat(layout.piece_scale()),
+            },
+            PreviewPiece,
+        ));
+    }
+}
 
-    // Find the king of the given color
-    let king = match pieces
-        .iter()
-        .find(|p| p.piece == PieceType::King && p.color == *color)
+// Shows what each side has taken next to the board, plus how far ahead in material
+// that side is. Everything comes from the move list so it always matches the shown position
+pub fn update_captured_tray(
+    history: Res<MoveHistory>,
+    orientation: Res<BoardOrientation>,
+    layout: Res<BoardLayout>,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+    q_tray: Query<Entity, With<CapturedTray>>,
+    mut commands: Commands,
+) {
+    if !history.is_changed()
+        && !orientation.is_changed()
+        && !layout.is_changed()
+        && !themes.is_changed()
     {
-        Some(k) => k,
-        None => return false, // No king found, can't have free squares
+        return;
+    }
+
+    for entity in q_tray.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let current = history.current_index();
+    let Some(position) = history.positions.get(current) else {
+        return;
     };
 
-    for x in 1..=8 {
-        for y in 1..=8 {
-            let to_tile = (x, y);
+    let material = |color: PieceColor| -> i32 {
+        position
+            .pieces()
+            .filter(|(_, p)| p.color == color)
+            .map(|(_, p)| p.kind.value() as i32)
+            .sum()
+    };
 
-            if let Some(target_piece) = pieces.iter().find(|p| p.position == to_tile) {
-                if target_piece.color == *color {
-                    continue;
-                }
-            }
+    for color in [PieceColor::White, PieceColor::Black] {
+        let mut captured: Vec<(PieceType, PieceColor)> = history
+            .moves
+            .iter()
+            .take(current)
+            .filter(|m| m.color == color)
+            .filter_map(|m| m.captured.map(|p| (p.kind, p.color)))
+            .collect();
+        captured.sort_by_key(|(kind, _)| kind.value());
+
+        // white's tray is below the board, black's above it
+        let start = match color {
+            PieceColor::White => layout.tile_to_world((1, 0)),
+            PieceColor::Black => layout.tile_to_world((1, 9)),
+        };
+        let (x, y) = (start.x - layout.tile_size / 4.0, start.y);
+        let spacing = TRAY_SPACING * layout.tile_size;
+
+        for (i, (kind, piece_color)) in captured.iter().enumerate() {
+            commands.spawn((
+                Sprite::from_image(
+                    asset_server.load(themes.current().piece_image(*kind, *piece_color)),
+                ),
+                Transform {
+                    translation: Vec3::new(x + i as f32 * spacing, y, 0.0),
+                    rotation: orientation.rotation(),
+                    scale: Vec3::splat(TRAY_PIECE_SIZE * layout.tile_size / PIECE_IMAGE_SIZE),
+                },
+                CapturedTray,
+            ));
+        }
 
-            if can_move_to_tile(king, to_tile, &pieces.iter().collect(), false).0 {
-                let mut hypothetical_pieces = pieces.clone();
+        let difference = material(color) - material(color.opposite());
+
+        if difference > 0 {
+            commands.spawn((
+                Text2d::new(format!("+{}", difference)),
+                TextFont {
+                    font_size: layout.tile_size * 0.22,
+                    ..default()
+                },
+                Transform {
+                    translation: Vec3::new(x + (captured.len() as f32 + 0.5) * spacing, y, 0.0),
+                    rotation: orientation.rotation(),
+                    ..default()
+                },
+                CapturedTray,
+            ));
+        }
+    }
+}
 
-                if let Some(p) = hypothetical_pieces.iter_mut().find(|p| {
-                    p.position == king.position && p.color == king.color && p.piece == king.piece
-                }) {
-                    p.position = to_tile;
-                }
+// Everything the game keeps track of goes back to how it was at startup,
+// the pieces themselves get set up again by the pieces plugin
+pub fn reset_game(
+    mut events: EventReader<NewGame>,
+    mut game_state: ResMut<GameState>,
+    mut selections: ResMut<Selections>,
+    mut dragged: ResMut<DraggedPiece>,
+    mut history: ResMut<MoveHistory>,
+    mut orientation: ResMut<BoardOrientation>,
+    setup: Res<GameSetup>,
+) {
+    for NewGame { board } in events.read() {
+        println!("Starting a new game from {}", board.to_fen());
+
+        *game_state = GameState::new(*board);
+
+        selections.selected_piece = None;
+        selections.second_selected_piece = None;
+        selections.second_selected_tile = None;
+        selections.premove = None;
+
+        dragged.entity = None;
+        dragged.dropped = false;
+
+        // empty positions make record_move take the new starting position
+        history.nodes.clear();
+        history.line.clear();
+        history.moves.clear();
+        history.positions.clear();
+        history.cursor = 0;
+        history.viewing = None;
+        history.annotations.clear();
+        history.hints = 0;
+
+        // against the computer the human side goes at the bottom
+        match (setup.white, setup.black) {
+            (PlayerKind::Human, PlayerKind::Computer) => orientation.flipped = false,
+            (PlayerKind::Computer, PlayerKind::Human) => orientation.flipped = true,
+            _ => {}
+        }
+    }
+}
 
-                if let Some(_target_piece) = pieces.iter().find(|p| p.position == to_tile) {
-                    hypothetical_pieces.retain(|p| p.position != to_tile || p.color == *color);
-                }
+pub fn clear_game_over_dialog(mut dialog: ResMut<GameOverDialog>) {
+    dialog.message = None;
+}
 
-                //println!("Hypothetical pieces after move: {:?}", hypothetical_pieces);
+// Playing -> GameOver as soon as the game is decided (mate, stalemate or flag fall)
+pub fn check_game_over(game_state: Res<GameState>, mut next_state: ResMut<NextState<AppState>>) {
+    if game_state.is_over() {
+        next_state.set(AppState::GameOver);
+    }
+}
 
-                let is_king_in_check =
-                    is_king_in_check(color, &hypothetical_pieces.iter().collect::<Vec<_>>());
+#[allow(clippy::too_many_arguments)]
+pub fn game_over_dialog(
+    mut contexts: EguiContexts,
+    game_state: Res<GameState>,
+    mut history: ResMut<MoveHistory>,
+    mut dialog: ResMut<GameOverDialog>,
+    mut setup: ResMut<GameSetup>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut new_game: EventWriter<NewGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let ctx = contexts.ctx_mut();
+    let result = result_string(&game_state);
 
-                if is_king_in_check {
-                    continue;
+    egui::Modal::new(egui::Id::new("game_over")).show(ctx, |ui| {
+        ui.heading(format!("Game over ({})", result));
+        ui.label(result_reason(&game_state));
+
+        if let Some(message) = &dialog.message {
+            ui.label(message);
+        }
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            // same starting position, but the players switch colors
+            if ui.button("Rematch").clicked() {
+                let (white, black) = (setup.white, setup.black);
+                setup.white = black;
+                setup.black = white;
+
+                // two humans just swap seats, so the board gets turned around
+                if white == black {
+                    orientation.flipped = !orientation.flipped;
                 }
 
-                println!("Found free square at {:?}", to_tile);
+                new_game.write(NewGame {
+                    board: history.positions.first().copied().unwrap_or_default(),
+                });
+                next_state.set(AppState::Playing);
+            }
+
+            if ui.button("New game").clicked() {
+                next_state.set(AppState::GameSetup);
+            }
 
-                return true;
+            if ui.button("Save PGN").clicked() {
+                dialog.message = Some(match save_pgn(&game_to_pgn(&history, &setup, result)) {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Couldn't save the game: {}", e),
+                });
             }
-        }
-    }
 
-    false
+            // closes the dialog and goes to the start of the game to step through it
+            if ui.button("Analyze").clicked() {
+                history.view(0);
+                next_state.set(AppState::Analysis);
+            }
+        });
+    });
 }
 
-pub fn castle(
-    mut castle_event: EventReader<Castle>,
-    mut query_set: ParamSet<(
-        Query<&mut ChessPiece>,
-        Query<(Entity, &ChessPiece, &mut Transform)>,
-    )>,
+pub fn animate_pieces(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut PieceAnimation)>,
+    mut commands: Commands,
 ) {
-    for Castle { king_pos } in castle_event.read() {
-        // Find the king entity by its position
-        // First, collect the king's color to avoid double mutable borrow
-        let king_info = query_set
-            .p1()
-            .iter_mut()
-            .find(|(_, piece, _)| piece.position == *king_pos)
-            .map(|(king_entity, king_piece, _)| (king_entity, king_piece.color));
-
-        if let Some((_king_entity, king_color)) = king_info {
-            // Determine if this is kingside or queenside castling
-            let (rook_from, rook_to) = if king_pos.0 > 4 {
-                // Kingside castling
-                ((8, king_pos.1), (king_pos.0 - 1, king_pos.1))
-            } else {
-                // Queenside castling
-                ((1, king_pos.1), (king_pos.0 + 1, king_pos.1))
-            };
+    for (entity, mut transform, mut animation) in query.iter_mut() {
+        animation.timer.tick(time.delta());
 
-            // Move the rook
-            let mut p1 = query_set.p1();
-            let mut rook_iter = p1.iter_mut();
-            let rook_info = rook_iter.find(|(_, piece, _)| {
-                piece.position == rook_from
-                    && piece.color == king_color
-                    && piece.piece == PieceType::Rook
-            });
+        // smoothstep so the pieces ease in and out
+        let t = animation.timer.fraction();
+        let eased = t * t * (3.0 - 2.0 * t);
+        transform.translation = animation.start.lerp(animation.end, eased);
 
-            if let Some((rook_entity, _rook_piece, _)) = rook_info {
-                if let Ok(mut rook) = query_set.p0().get_mut(rook_entity) {
-                    rook.position = rook_to;
-                }
-                if let Ok((_, _, mut transform)) = query_set.p1().get_mut(rook_entity) {
-                    let (x, y) = tile_to_screen_coord(rook_to);
-                    transform.translation.x = x;
-                    transform.translation.y = y;
-                }
-            }
+        if animation.timer.finished() {
+            commands.entity(entity).remove::<PieceAnimation>();
         }
     }
 }
 
-fn can_move_to_tile(
-    piece: &ChessPiece,
-    to_tile: (u8, u8),
-    pieces: &Vec<&ChessPiece>,
-    attacking: bool,
-) -> (bool, bool) {
-    let is_occupied = |pos: (u8, u8)| pieces.iter().any(|p| p.position == pos);
+pub fn animate_captures(
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut Sprite, &mut CaptureAnimation)>,
+    mut commands: Commands,
+) {
+    for (entity, mut transform, mut sprite, mut animation) in query.iter_mut() {
+        animation.timer.tick(time.delta());
 
-    match piece.piece {
-        PieceType::Pawn => (can_pawn_move(piece, to_tile, pieces, attacking), false),
-        PieceType::Rook => (can_rook_move(piece, to_tile, pieces, &is_occupied), false),
-        PieceType::Bishop => (can_bishop_move(piece, to_tile, pieces, &is_occupied), false),
-        PieceType::Queen => (can_queen_move(piece, to_tile, pieces, &is_occupied), false),
-        PieceType::Knight => (can_knight_move(piece, to_tile, pieces), false),
-        PieceType::King => can_king_move(piece, to_tile, pieces),
-    }
-}
+        let t = animation.timer.fraction();
+        transform.scale = animation.start_scale * (1.0 - t);
+        sprite.color = sprite.color.with_alpha(1.0 - t);
 
-fn is_king_in_check(color: &PieceColor, pieces: &Vec<&ChessPiece>) -> bool {
-    // 1. Find the king of the given color
-    let king = match pieces
-        .iter()
-        .find(|p| p.piece == PieceType::King && p.color == *color)
-    {
-        Some(k) => k,
-        None => return false, // No king found, can't be in check
-    };
-    let king_pos = king.position;
-
-    // 2. For each enemy piece, check if it can move to the king's position
-    for piece in pieces.iter().filter(|p| p.color != *color) {
-        let can_attack = match piece.piece {
-            PieceType::Pawn => can_pawn_move(piece, king_pos, pieces, false),
-            PieceType::Rook => can_rook_move(piece, king_pos, pieces, &|pos| {
-                pieces.iter().any(|p| p.position == pos)
-            }),
-            PieceType::Bishop => can_bishop_move(piece, king_pos, pieces, &|pos| {
-                pieces.iter().any(|p| p.position == pos)
-            }),
-            PieceType::Queen => can_queen_move(piece, king_pos, pieces, &|pos| {
-                pieces.iter().any(|p| p.position == pos)
-            }),
-            PieceType::Knight => can_knight_move(piece, king_pos, pieces),
-            PieceType::King => {
-                let dx = (king_pos.0 as i8 - piece.position.0 as i8).abs();
-                let dy = (king_pos.1 as i8 - piece.position.1 as i8).abs();
-                dx <= 1 && dy <= 1
-            }
-        };
-        if can_attack {
-            return true;
+        if animation.timer.finished() {
+            commands.entity(entity).despawn();
         }
     }
-    false
 }
 
-pub fn update_ui(mut text_query: Query<&mut Text>, game_state: Res<GameState>) {
-    for mut text in text_query.iter_mut() {
-        *text = format!(
-            "Turn: {:?}\nCheck: {:?}\nCheckmate: {:?}\nStalemate: {:?}",
-            game_state.turn, game_state.check, game_state.checkmate, game_state.stalemate
-        )
-        .into();
-    }
+fn slide_to_tile(
+    commands: &mut Commands,
+    entity: Entity,
+    start: Vec3,
+    tile: (u8, u8),
+    layout: &BoardLayout,
+    animation_settings: &AnimationSettings,
+) {
+    commands.entity(entity).insert(PieceAnimation {
+        start,
+        end: layout.tile_to_world(tile).extend(0.0),
+        timer: Timer::from_seconds(animation_settings.move_duration, TimerMode::Once),
+    });
 }
 
-// well so I can get the selected tile not just the screen coordinates
-fn screen_coord_to_tile(screen_coord: (f32, f32)) -> (u8, u8) {
-    let tile_pos = (
-        // divided by 1.5 cause the offset was too big but only for this otherwise it's fine
-        ((screen_coord.0 - BOARD_OFFSET.x / 1.5) / TILE_SIZE).floor() as u8 + 1,
-        ((screen_coord.1 - BOARD_OFFSET.y / 1.5) / TILE_SIZE).floor() as u8 + 1,
-    );
+// Highlights are spawned hidden once and only moved around / shown afterwards.
+// They're 1x1 sprites that get scaled up to the tile size
+pub fn set_up_highlights(mut commands: Commands, themes: Res<Themes>) {
+    let theme = themes.current();
+
+    for highlight in [LastMoveHighlight::From, LastMoveHighlight::To] {
+        commands.spawn((
+            Sprite::from_color(theme.last_move_color(), Vec2::ONE),
+            Transform::from_xyz(0.0, 0.0, HIGHLIGHT_Z),
+            Visibility::Hidden,
+            highlight,
+        ));
+    }
 
-    if (screen_coord.0 - BOARD_OFFSET.x / 1.5) < 0.0
-        || (screen_coord.1 - BOARD_OFFSET.y / 1.5) < 0.0
-        || tile_pos.0 > 8
-        || tile_pos.1 > 8
-    {
-        return (0, 0);
+    for highlight in [PremoveHighlight::From, PremoveHighlight::To] {
+        commands.spawn((
+            Sprite::from_color(theme.premove_color(), Vec2::ONE),
+            Transform::from_xyz(0.0, 0.0, HIGHLIGHT_Z),
+            Visibility::Hidden,
+            highlight,
+        ));
     }
 
-    // this does not need to be called but I want to see the output
-    tile_to_screen_coord(tile_pos);
+    commands.spawn((
+        Sprite::from_color(theme.check_color(), Vec2::ONE),
+        Transform::from_xyz(0.0, 0.0, HIGHLIGHT_Z),
+        Visibility::Hidden,
+        CheckHighlight,
+    ));
+}
 
-    tile_pos
+fn place_on_tile(transform: &mut Transform, tile: (u8, u8), layout: &BoardLayout) {
+    transform.translation = layout.tile_to_world(tile).extend(HIGHLIGHT_Z);
+    transform.scale = Vec3::splat(layout.tile_size);
 }
 
-// I don't think I actually need this function
-fn tile_to_screen_coord(tile: (u8, u8)) -> (f32, f32) {
-    let screen_coord = (
-        BOARD_OFFSET.x + (tile.0 as f32 - 1.0) * TILE_SIZE,
-        BOARD_OFFSET.y + (tile.1 as f32 - 1.0) * TILE_SIZE,
-    );
+pub fn highlight_last_move(
+    history: Res<MoveHistory>,
+    layout: Res<BoardLayout>,
+    mut query: Query<(&LastMoveHighlight, &mut Transform, &mut Visibility)>,
+) {
+    if !history.is_changed() && !layout.is_changed() {
+        return;
+    }
 
-    println!(
-        "Tile coords: {:?} -> Screen coords: {:?}",
-        tile, screen_coord
-    );
+    for (highlight, mut transform, mut visibility) in query.iter_mut() {
+        // nothing to show at the start of a (new) game
+        let Some(last) = history.last_move() else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        let tile = match highlight {
+            LastMoveHighlight::From => last.from,
+            LastMoveHighlight::To => last.to,
+        };
 
-    screen_coord
+        place_on_tile(&mut transform, tile, &layout);
+        *visibility = Visibility::Visible;
+    }
 }
 
-// Can Moves
+pub fn highlight_premove(
+    selections: Res<Selections>,
+    layout: Res<BoardLayout>,
+    mut query: Query<(&PremoveHighlight, &mut Transform, &mut Visibility)>,
+) {
+    if !selections.is_changed() && !layout.is_changed() {
+        return;
+    }
 
-fn can_king_move(piece: &ChessPiece, to_tile: (u8, u8), pieces: &Vec<&ChessPiece>) -> (bool, bool) {
-    let dx = (to_tile.0 as i8 - piece.position.0 as i8).abs();
-    let dy = (to_tile.1 as i8 - piece.position.1 as i8).abs();
+    for (highlight, mut transform, mut visibility) in query.iter_mut() {
+        let Some(Premove { from, to, .. }) = selections.premove else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
 
-    // Normal king move
-    if dx <= 1 && dy <= 1 {
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return (target.color != piece.color, false);
-        }
-        return (true, false);
+        let tile = match highlight {
+            PremoveHighlight::From => from,
+            PremoveHighlight::To => to,
+        };
+
+        place_on_tile(&mut transform, tile, &layout);
+        *visibility = Visibility::Visible;
     }
+}
 
-    // Castling: king moves 2 squares horizontally on the same rank
-    if dy == 0 && dx == 2 {
-        let rank = piece.position.1;
-        let kingside = to_tile.0 > piece.position.0;
-        let rook_file = if kingside { 8 } else { 1 };
-        let rook_pos = (rook_file, rank);
+pub fn highlight_check(
+    game_state: Res<GameState>,
+    layout: Res<BoardLayout>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<CheckHighlight>>,
+) {
+    if !game_state.is_changed() && !layout.is_changed() {
+        return;
+    }
 
-        if let Some(_rook) = pieces.iter().find(|p| {
-            p.position == rook_pos && p.piece == PieceType::Rook && p.color == piece.color
-        }) {
-            // Check tiles between king and rook are empty
-            let range = if kingside {
-                (piece.position.0 + 1)..rook_file
-            } else {
-                (rook_file + 1)..piece.position.0
-            };
+    // check is always about the side that has to move now
+    let king = game_state.board.king_square(game_state.turn);
 
-            for file in range {
-                if pieces.iter().any(|p| p.position == (file, rank)) {
-                    return (false, false);
-                }
+    for (mut transform, mut visibility) in query.iter_mut() {
+        match king {
+            Some(king) if game_state.check => {
+                place_on_tile(&mut transform, tile(king), &layout);
+                *visibility = Visibility::Visible;
             }
-
-            return (true, true);
+            _ => *visibility = Visibility::Hidden,
         }
     }
+}
+
+// F flips the board, T toggles flipping it after every move
+pub fn flip_board_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut orientation: ResMut<BoardOrientation>,
+) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        orientation.flipped = !orientation.flipped;
+    }
 
-    (false, false)
+    if keys.just_pressed(KeyCode::KeyT) {
+        orientation.auto_flip = !orientation.auto_flip;
+        println!("Auto flip: {}", orientation.auto_flip);
+    }
 }
 
-fn can_knight_move(piece: &ChessPiece, to_tile: (u8, u8), pieces: &Vec<&ChessPiece>) -> bool {
-    let dx = (to_tile.0 as i8 - piece.position.0 as i8).abs();
-    let dy = (to_tile.1 as i8 - piece.position.1 as i8).abs();
-    if (dx == 2 && dy == 1) || (dx == 1 && dy == 2) {
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else {
-        false
-    }
-}
-
-fn can_queen_move(
-    piece: &ChessPiece,
-    to_tile: (u8, u8),
-    pieces: &Vec<&ChessPiece>,
-    is_occupied: &dyn Fn((u8, u8)) -> bool,
-) -> bool {
-    let dx = to_tile.0 as i8 - piece.position.0 as i8;
-    let dy = to_tile.1 as i8 - piece.position.1 as i8;
-    if dx.abs() == dy.abs() {
-        // Diagonal like bishop
-        let steps = dx.abs();
-        let x_step = if dx > 0 { 1 } else { -1 };
-        let y_step = if dy > 0 { 1 } else { -1 };
-        for i in 1..steps {
-            let x = (piece.position.0 as i8 + i * x_step) as u8;
-            let y = (piece.position.1 as i8 + i * y_step) as u8;
-            if is_occupied((x, y)) {
-                return false;
-            }
-        }
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else if piece.position.0 == to_tile.0 {
-        // Vertical like rook
-        let range = if piece.position.1 < to_tile.1 {
-            (piece.position.1 + 1)..to_tile.1
-        } else {
-            (to_tile.1 + 1)..piece.position.1
-        };
-        for y in range {
-            if is_occupied((piece.position.0, y)) {
-                return false;
-            }
-        }
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else if piece.position.1 == to_tile.1 {
-        // Horizontal like rook
-        let range = if piece.position.0 < to_tile.0 {
-            (piece.position.0 + 1)..to_tile.0
-        } else {
-            (to_tile.0 + 1)..piece.position.0
-        };
-        for x in range {
-            if is_occupied((x, piece.position.1)) {
-                return false;
-            }
+pub fn auto_flip_board(game_state: Res<GameState>, mut orientation: ResMut<BoardOrientation>) {
+    if !orientation.auto_flip || !(game_state.is_changed() || orientation.is_changed()) {
+        return;
+    }
+
+    let flipped = game_state.turn == PieceColor::Black;
+
+    // only write when it actually changes so apply_orientation doesn't run every frame
+    if orientation.flipped != flipped {
+        orientation.flipped = flipped;
+    }
+}
+
+// Flipping just turns the camera around the middle of the board, that way
+// clicks (viewport_to_world) and everything on the board follow automatically.
+// Pieces get turned the same way so they're not upside down
+pub fn apply_orientation(
+    orientation: Res<BoardOrientation>,
+    mut q_camera: Query<&mut Transform, With<Camera2d>>,
+    mut q_pieces: Query<&mut Transform, (With<ChessPiece>, Without<Camera2d>)>,
+    q_added: Query<Entity, Added<ChessPiece>>,
+) {
+    let rotation = orientation.rotation();
+
+    if orientation.is_changed() {
+        for mut transform in q_camera.iter_mut() {
+            transform.rotation = rotation;
         }
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
+
+        for mut transform in q_pieces.iter_mut() {
+            transform.rotation = rotation;
         }
-        true
     } else {
-        false
-    }
-}
-
-fn can_bishop_move(
-    piece: &ChessPiece,
-    to_tile: (u8, u8),
-    pieces: &Vec<&ChessPiece>,
-    is_occupied: &dyn Fn((u8, u8)) -> bool,
-) -> bool {
-    let dx = to_tile.0 as i8 - piece.position.0 as i8;
-    let dy = to_tile.1 as i8 - piece.position.1 as i8;
-    if dx.abs() == dy.abs() {
-        let steps = dx.abs();
-        let x_step = if dx > 0 { 1 } else { -1 };
-        let y_step = if dy > 0 { 1 } else { -1 };
-        for i in 1..steps {
-            let x = (piece.position.0 as i8 + i * x_step) as u8;
-            let y = (piece.position.1 as i8 + i * y_step) as u8;
-            if is_occupied((x, y)) {
-                return false;
+        // pieces spawned while the board is already flipped
+        for entity in q_added.iter() {
+            if let Ok(mut transform) = q_pieces.get_mut(entity) {
+                transform.rotation = rotation;
             }
         }
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else {
-        false
     }
 }
 
-fn can_rook_move(
-    piece: &ChessPiece,
-    to_tile: (u8, u8),
-    pieces: &Vec<&ChessPiece>,
-    is_occupied: &dyn Fn((u8, u8)) -> bool,
-) -> bool {
-    // Rooks move in straight lines
-    if piece.position.0 == to_tile.0 {
-        // Vertical move
-        let range = if piece.position.1 < to_tile.1 {
-            (piece.position.1 + 1)..to_tile.1
-        } else {
-            (to_tile.1 + 1)..piece.position.1
-        };
-        for y in range {
-            if is_occupied((piece.position.0, y)) {
-                return false;
-            }
-        }
-        // Can't capture own piece
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else if piece.position.1 == to_tile.1 {
-        // Horizontal move
-        let range = if piece.position.0 < to_tile.0 {
-            (piece.position.0 + 1)..to_tile.0
-        } else {
-            (to_tile.0 + 1)..piece.position.0
-        };
-        for x in range {
-            if is_occupied((x, piece.position.1)) {
-                return false;
-            }
-        }
-        if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-            return target.color != piece.color;
-        }
-        true
-    } else {
-        false
+// C goes hidden -> around the board -> inside the squares -> hidden
+pub fn coordinate_labels_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut labels: ResMut<CoordinateLabels>,
+) {
+    if !keys.just_pressed(KeyCode::KeyC) {
+        return;
     }
+
+    labels.style = match labels.style {
+        CoordinateStyle::Hidden => CoordinateStyle::Edges,
+        CoordinateStyle::Edges => CoordinateStyle::InsideSquares,
+        CoordinateStyle::InsideSquares => CoordinateStyle::Hidden,
+    };
 }
 
-fn can_pawn_move(
-    piece: &ChessPiece,
-    to_tile: (u8, u8),
-    pieces: &Vec<&ChessPiece>,
-    attacking: bool,
-) -> bool {
-    if attacking {
-        return can_pawn_attack(piece, to_tile, pieces);
+// Files go along the bottom and ranks along the left side of the board the way
+// it's looked at, so with a flipped board they're on the other sides in the world
+pub fn update_coordinate_labels(
+    labels: Res<CoordinateLabels>,
+    orientation: Res<BoardOrientation>,
+    layout: Res<BoardLayout>,
+    themes: Res<Themes>,
+    q_labels: Query<Entity, With<CoordinateLabel>>,
+    mut commands: Commands,
+) {
+    if !labels.is_changed()
+        && !orientation.is_changed()
+        && !layout.is_changed()
+        && !themes.is_changed()
+    {
+        return;
     }
 
-    let dy = to_tile.1 as i8 - piece.position.1 as i8;
-    let dx = (to_tile.0 as i8 - piece.position.0 as i8).abs();
+    let theme = themes.current();
 
-    match piece.color {
-        PieceColor::White => {
-            // White pawns move up (increasing y)
-            if piece.position.1 == 2 && dy == 2 && dx == 0 {
-                // First move, two squares forward
-                let intermediate = (piece.position.0, piece.position.1 + 1);
-                if !pieces.iter().any(|p| p.position == intermediate)
-                    && !pieces.iter().any(|p| p.position == to_tile)
-                {
-                    return true;
-                }
-            } else if dy == 1 && dx == 0 {
-                // Normal move, one square forward
-                if !pieces.iter().any(|p| p.position == to_tile) {
-                    return true;
-                }
-            } else if dy == 1 && dx == 1 {
-                // Capture diagonally
-                if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-                    return target.color != piece.color;
-                }
+    for entity in q_labels.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if labels.style == CoordinateStyle::Hidden {
+        return;
+    }
+
+    // -1 is towards the bottom / left of the screen, 1 the other way
+    let towards_viewer = if orientation.flipped { 1.0 } else { -1.0 };
+    let (bottom_rank, left_file) = if orientation.flipped { (8, 8) } else { (1, 1) };
+    let tile_size = layout.tile_size;
+
+    let mut spawn_label = |text: String, tile: (u8, u8), offset: Vec2, color: Color| {
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font_size: tile_size * 0.2,
+                ..default()
+            },
+            TextColor(color),
+            Transform {
+                translation: (layout.tile_to_world(tile) + offset * tile_size).extend(LABEL_Z),
+                rotation: orientation.rotation(),
+                ..default()
+            },
+            CoordinateLabel,
+        ));
+    };
+
+    // text on a square gets the other square color so it's readable
+    let contrast = |tile: (u8, u8)| theme.square_color(!BoardSquare(tile).is_dark());
+
+    for i in 1..=8u8 {
+        let file = ((b'a' + i - 1) as char).to_string();
+        let rank = i.to_string();
+
+        match labels.style {
+            CoordinateStyle::Edges => {
+                // just outside the squares, between them and the captured pieces
+                spawn_label(
+                    file,
+                    (i, bottom_rank),
+                    Vec2::new(0.0, 0.64 * towards_viewer),
+                    theme.label_color(),
+                );
+                spawn_label(
+                    rank,
+                    (left_file, i),
+                    Vec2::new(0.64 * towards_viewer, 0.0),
+                    theme.label_color(),
+                );
             }
-        }
-        PieceColor::Black => {
-            // Black pawns move down (decreasing y)
-            if piece.position.1 == 7 && dy == -2 && dx == 0 {
-                // First move, two squares forward
-                let intermediate = (piece.position.0, piece.position.1 - 1);
-                if !pieces.iter().any(|p| p.position == intermediate)
-                    && !pieces.iter().any(|p| p.position == to_tile)
-                {
-                    return true;
-                }
-            } else if dy == -1 && dx == 0 {
-                // Normal move, one square forward
-                if !pieces.iter().any(|p| p.position == to_tile) {
-                    return true;
-                }
-            } else if dy == -1 && dx == 1 {
-                // Capture diagonally
-                if let Some(target) = pieces.iter().find(|p| p.position == to_tile) {
-                    return target.color != piece.color;
-                }
+            CoordinateStyle::InsideSquares => {
+                // files in the bottom right corner, ranks in the top left one
+                spawn_label(
+                    file,
+                    (i, bottom_rank),
+                    Vec2::new(-0.38, 0.38) * towards_viewer,
+                    contrast((i, bottom_rank)),
+                );
+                spawn_label(
+                    rank,
+                    (left_file, i),
+                    Vec2::new(0.38, -0.38) * towards_viewer,
+                    contrast((left_file, i)),
+                );
             }
+            CoordinateStyle::Hidden => {}
         }
     }
-    false
 }
 
-fn can_pawn_attack(piece: &ChessPiece, to_tile: (u8, u8), pieces: &Vec<&ChessPiece>) -> bool {
-    let dy = to_tile.1 as i8 - piece.position.1 as i8;
-    let dx = (to_tile.0 as i8 - piece.position.0 as i8).abs();
+// Keeps the layout in line with the window size. The camera only draws the
+// part of the window between the side panels so the board never ends up under them
+pub fn update_board_layout(
+    q_window: Query<&Window>,
+    mut q_camera: Query<&mut Camera, With<Camera2d>>,
+    mut layout: ResMut<BoardLayout>,
+) {
+    let Ok(window) = q_window.single() else {
+        return;
+    };
+
+    // minimized windows are 0x0
+    if window.width() < 1.0 || window.height() < 1.0 {
+        return;
+    }
 
-    (match piece.color {
-        PieceColor::White => dy == 1 && dx == 1,
-        PieceColor::Black => dy == -1 && dx == 1,
-    }) && pieces
-        .iter()
-        .any(|p| p.position == to_tile && p.color != piece.color)
+    let new_layout = BoardLayout::new(window.size());
+
+    // only write when it actually changes so everything on the board isn't moved every frame
+    if *layout != new_layout {
+        println!("Board layout: {:?}", new_layout);
+        *layout = new_layout;
+    }
+
+    let scale_factor = window.scale_factor();
+    let physical_position = (layout.viewport_position * scale_factor).as_uvec2();
+    let physical_size = (layout.viewport_size * scale_factor)
+        .as_uvec2()
+        .min(window.physical_size().saturating_sub(physical_position))
+        .max(UVec2::ONE);
+
+    for mut camera in q_camera.iter_mut() {
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|v| (v.physical_position, v.physical_size));
+
+        if current != Some((physical_position, physical_size)) {
+            camera.viewport = Some(Viewport {
+                physical_position,
+                physical_size,
+                ..default()
+            });
+        }
+    }
 }
 
 // I think I needed this once but since I now query the pieces directly
@@ -7,13 +7,56 @@ use bevy::{
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+pub mod ai;
+pub mod clock;
+pub mod editor;
+pub mod engine;
+pub mod evaluation;
 pub mod game;
+pub mod menu;
+pub mod perft;
 pub mod pieces;
+pub mod settings;
+pub mod sound;
+pub mod states;
+pub mod theme;
+pub mod uci;
 
+use ai::AiPlugin;
+use clock::ClockPlugin;
+use editor::EditorPlugin;
+use evaluation::EvaluationPlugin;
 use game::GamePlugin;
+use menu::MenuPlugin;
 use pieces::PiecesPlugin;
+use settings::{SettingsPlugin, resources::Settings, systems::inspector_enabled};
+use sound::SoundPlugin;
+use states::AppState;
+use theme::ThemePlugin;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    // uci and perft run in the terminal without opening the window
+    match args.get(1).map(String::as_str) {
+        // just the engine, for other chess GUIs
+        Some("uci") => {
+            uci::run();
+            return;
+        }
+        Some("perft") => {
+            if let Err(error) = perft::run(&args[2..]) {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // the window needs its size before the app is built
+    let settings = Settings::load();
+
     // Important to keep the correct order
 
     App::new()
@@ -21,7 +64,8 @@ fn main() {
             primary_window: Some(Window {
                 title: "Chess in Rust".into(),
                 position: WindowPosition::Centered(Primary),
-                resolution: (900.0, 900.0).into(),
+                // extra width is for the side panels (clocks and move list)
+                resolution: (settings.window_width, settings.window_height).into(),
                 present_mode: PresentMode::AutoVsync,
                 fit_canvas_to_parent: true,
                 window_theme: Some(WindowTheme::Dark),
@@ -29,14 +73,27 @@ fn main() {
             }),
             ..default()
         }),))
+        // needs the StatesPlugin from DefaultPlugins
+        .init_state::<AppState>()
+        .register_type::<AppState>()
+        .insert_resource(settings)
         .add_plugins(EguiPlugin {
             enable_multipass_for_primary_context: true,
         })
         .add_plugins(
-            WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::Escape)),
+            WorldInspectorPlugin::default()
+                .run_if(input_toggle_active(false, KeyCode::Escape).and(inspector_enabled)),
         )
+        .add_plugins(ThemePlugin)
         .add_plugins(PiecesPlugin)
         .add_plugins(GamePlugin)
+        .add_plugins(ClockPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(EditorPlugin)
+        .add_plugins(AiPlugin)
+        .add_plugins(EvaluationPlugin)
+        .add_plugins(SoundPlugin)
+        .add_plugins(SettingsPlugin)
         .add_systems(Startup, set_up_bevy)
         .add_systems(Update, update)
         .run();
@@ -45,24 +102,8 @@ fn main() {
 fn set_up_bevy(mut commands: Commands) {
     commands.spawn((
         Camera2d { ..default() },
-        Transform::from_xyz(450.0, 450.0, 999.0),
-    ));
-
-    commands.spawn((
-        Text::new("test \ntest"),
-        TextFont {
-            font_size: 11.0,
-            ..Default::default()
-        },
-        Node {
-            position_type: PositionType::Absolute,
-            width: Val::Px(110.0),
-            height: Val::Px(80.0),
-
-            top: Val::Px(5.0),
-            left: Val::Px(5.0),
-            ..default()
-        },
+        // the board is centered on (0, 0)
+        Transform::from_xyz(0.0, 0.0, 999.0),
     ));
 }
 
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+
+mod systems;
+
+use systems::*;
+
+use crate::states::AppState;
+
+pub struct MenuPlugin;
+
+// The screens before a game starts, the board is hidden behind them
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            EguiContextPass,
+            (
+                main_menu_ui.run_if(in_state(AppState::MainMenu)),
+                game_setup_ui.run_if(in_state(AppState::GameSetup)),
+            ),
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    clock::resources::{IncrementMode, TimeControl, TimeControlPreset},
+    engine::{board::Board, variants::Variant},
+    game::resources::{GameSetup, GameState, MoveHistory, NewGame, PlayerKind},
+    pieces::components::PieceColor,
+    settings::resources::SettingsWindow,
+    states::AppState,
+};
+
+pub fn main_menu_ui(
+    mut contexts: EguiContexts,
+    game_state: Res<GameState>,
+    history: Res<MoveHistory>,
+    mut new_game: EventWriter<NewGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut settings_window: ResMut<SettingsWindow>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(200.0);
+            ui.heading(egui::RichText::new("Chess in Rust").size(48.0));
+            ui.add_space(40.0);
+
+            let button = |ui: &mut egui::Ui, text: &str| {
+                ui.add_sized([240.0, 40.0], egui::Button::new(text))
+                    .clicked()
+            };
+
+            // coming back from the board with the game still going
+            if !history.moves.is_empty() && !game_state.is_over() && button(ui, "Continue") {
+                next_state.set(AppState::Playing);
+            }
+
+            if button(ui, "New game") {
+                next_state.set(AppState::GameSetup);
+            }
+
+            // both sides can be moved freely and there's no clock
+            if button(ui, "Analysis board") {
+                new_game.write(NewGame {
+                    board: Board::start(),
+                });
+                next_state.set(AppState::Analysis);
+            }
+
+            if button(ui, "Board editor") {
+                next_state.set(AppState::Editor);
+            }
+
+            if button(ui, "Settings") {
+                settings_window.open = true;
+            }
+
+            if button(ui, "Quit") {
+                exit.write(AppExit::Success);
+            }
+        });
+    });
+}
+
+pub fn game_setup_ui(
+    mut contexts: EguiContexts,
+    mut setup: ResMut<GameSetup>,
+    mut new_game: EventWriter<NewGame>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("New game");
+        ui.add_space(10.0);
+
+        egui::Grid::new("game_setup")
+            .num_columns(2)
+            .spacing([40.0, 12.0])
+            .show(ui, |ui| {
+                for color in [PieceColor::White, PieceColor::Black] {
+                    ui.label(format!("{:?}", color));
+
+                    let player = match color {
+                        PieceColor::White => &mut setup.white,
+                        PieceColor::Black => &mut setup.black,
+                    };
+
+                    ui.horizontal(|ui| {
+                        ui.radio_value(player, PlayerKind::Human, "Human");
+                        ui.radio_value(player, PlayerKind::Computer, "Computer");
+                    });
+                    ui.end_row();
+                }
+
+                if setup.white == PlayerKind::Computer || setup.black == PlayerKind::Computer {
+                    ui.label("Computer depth");
+                    ui.add(egui::Slider::new(&mut setup.computer_depth, 1..=8));
+                    ui.end_row();
+                }
+
+                ui.label("Time control");
+                ui.vertical(|ui| time_control_picker(ui, &mut setup.time_control));
+                ui.end_row();
+
+                ui.label("Variant");
+                ui.horizontal(|ui| {
+                    for variant in Variant::ALL {
+                        ui.radio_value(&mut setup.variant, variant, variant.name());
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Starting FEN");
+                ui.vertical(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut setup.start_fen)
+                            .desired_width(500.0)
+                            .hint_text("empty for the variant's starting position"),
+                    );
+
+                    if ui.button("Set up in the board editor").clicked() {
+                        next_state.set(AppState::Editor);
+                    }
+                });
+                ui.end_row();
+            });
+
+        // the FEN gets checked while typing so the error shows up right away
+        let board = setup.starting_board();
+
+        if let Err(error) = &board {
+            ui.colored_label(egui::Color32::RED, format!("Invalid FEN: {}", error));
+        }
+
+        ui.add_space(20.0);
+
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                next_state.set(AppState::MainMenu);
+            }
+
+            let start = ui
+                .add_enabled(board.is_ok(), egui::Button::new("Start"))
+                .clicked();
+
+            if let (true, Ok(board)) = (start, board) {
+                new_game.write(NewGame { board });
+                next_state.set(AppState::Playing);
+            }
+        });
+    });
+}
+
+fn time_control_picker(ui: &mut egui::Ui, time_control: &mut Option<TimeControl>) {
+    let mode = time_control.map_or(IncrementMode::Fischer, |t| t.mode);
+
+    ui.horizontal(|ui| {
+        if ui
+            .selectable_label(time_control.is_none(), "No clock")
+            .clicked()
+        {
+            *time_control = None;
+        }
+
+        for preset in TimeControlPreset::ALL {
+            let preset_control = preset.time_control(mode);
+            let text = format!(
+                "{:?} {}+{}",
+                preset,
+                preset_control.base / 60.0,
+                preset_control.increment
+            );
+
+            if ui
+                .selectable_label(*time_control == Some(preset_control), text)
+                .clicked()
+            {
+                *time_control = Some(preset_control);
+            }
+        }
+    });
+
+    if let Some(time_control) = time_control {
+        ui.horizontal(|ui| {
+            for mode in [
+                IncrementMode::Fischer,
+                IncrementMode::Bronstein,
+                IncrementMode::SimpleDelay,
+            ] {
+                ui.radio_value(&mut time_control.mode, mode, format!("{:?}", mode));
+            }
+        });
+    }
+}
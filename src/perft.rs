@@ -0,0 +1,117 @@
+use std::time::Instant;
+
+use crate::engine::{
+    board::Board,
+    notation::move_to_uci,
+    perft::{SUITE, divide, perft},
+};
+
+// The suite stops here unless it's asked for more, the deeper counts take minutes
+const SUITE_DEPTH: u32 = 4;
+
+// perft from the command line, no window:
+//   perft 5                  divide from the start position
+//   perft 3 <fen>            divide from any position
+//   perft --suite [depth]    checks the move generator against the known counts
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("--suite") => {
+            let depth = match args.get(1) {
+                Some(depth) => parse_depth(depth)?,
+                None => SUITE_DEPTH,
+            };
+
+            run_suite(depth)
+        }
+        Some(depth) => {
+            let depth = parse_depth(depth)?;
+            let board = if args.len() > 1 {
+                Board::from_fen(&args[1..].join(" "))?.playable()?
+            } else {
+                Board::start()
+            };
+
+            run_divide(&board, depth);
+            Ok(())
+        }
+        None => Err("usage: perft <depth> [fen] or perft --suite [depth]".to_string()),
+    }
+}
+
+// perft 0 is always 1 and divide has no moves to show for it, so it starts at 1
+fn parse_depth(text: &str) -> Result<u32, String> {
+    match text.parse() {
+        Ok(0) => Err("the depth has to be at least 1".to_string()),
+        Ok(depth) => Ok(depth),
+        Err(_) => Err(format!("{} isn't a depth", text)),
+    }
+}
+
+// Same layout as Stockfish's "go perft", so the two can be diffed
+fn run_divide(board: &Board, depth: u32) {
+    let start = Instant::now();
+    let mut moves: Vec<(String, u64)> = divide(board, depth)
+        .into_iter()
+        .map(|(mv, nodes)| (move_to_uci(board, mv, false), nodes))
+        .collect();
+
+    moves.sort();
+
+    for (mv, nodes) in &moves {
+        println!("{}: {}", mv, nodes);
+    }
+
+    let total: u64 = moves.iter().map(|(_, nodes)| nodes).sum();
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!();
+    println!("Nodes searched: {}", total);
+    println!(
+        "{:.2}s ({:.0} nodes/s)",
+        seconds,
+        total as f64 / seconds.max(0.001)
+    );
+}
+
+fn run_suite(max_depth: u32) -> Result<(), String> {
+    let start = Instant::now();
+    let mut failed = 0;
+
+    for position in &SUITE {
+        let board = Board::from_fen(position.fen)
+            .map_err(|e| format!("{} has a bad FEN: {}", position.name, e))?;
+
+        for (depth, &expected) in (1..=max_depth).zip(position.counts) {
+            let nodes = perft(&board, depth);
+
+            if nodes == expected {
+                println!("{} depth {}: {} ok", position.name, depth, nodes);
+            } else {
+                println!(
+                    "{} depth {}: {} WRONG, should be {}",
+                    position.name, depth, nodes, expected
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    println!("Done in {:.1}s", start.elapsed().as_secs_f32());
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(format!("{} counts were wrong", failed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_zero_is_rejected() {
+        assert!(parse_depth("0").is_err());
+        assert!(parse_depth("x").is_err());
+        assert_eq!(parse_depth("3"), Ok(3));
+    }
+}
@@ -10,7 +10,28 @@ pub struct ChessPiece {
     pub piece: PieceType,
     pub color: PieceColor,
     pub position: (u8, u8),
-    value: u8,
+}
+
+// The frame around the board
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct ChessBoard;
+
+// Picture of the squares for themes that have one, child of the frame
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct BoardImage;
+
+// One of the 64 squares, dark or light depends on the tile
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct BoardSquare(pub (u8, u8));
+
+impl BoardSquare {
+    // a1 is dark
+    pub fn is_dark(&self) -> bool {
+        (self.0.0 + self.0.1).is_multiple_of(2)
+    }
 }
 
 #[derive(Reflect, Clone, Debug, PartialEq, Eq, Default, Copy)]
@@ -31,24 +52,50 @@ pub enum PieceColor {
     Black,
 }
 
+impl PieceType {
+    // Material value, the pieces and the captured tray both go by this
+    pub fn value(&self) -> u8 {
+        match self {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+        }
+    }
+}
+
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
 // Nicer to create a new ChessPiece with this function
 // instead of manually setting all fields
 impl ChessPiece {
-    pub fn new(
-        name: &str,
-        piece: PieceType,
-        color: PieceColor,
-        position: (u8, u8),
-        value: u8,
-    ) -> ChessPiece {
+    pub fn new(name: &str, piece: PieceType, color: PieceColor, position: (u8, u8)) -> ChessPiece {
         ChessPiece {
             name: name.to_string(),
             piece,
             color,
             position,
-            value,
         }
     }
+
+    // Always from the type, so a promoted piece and the captured tray agree with it
+    pub fn value(&self) -> u8 {
+        self.piece.value()
+    }
+
+    // Pawn reached the last rank, the sprite gets swapped by sync_piece_sprites
+    pub fn promote(&mut self, piece: PieceType) {
+        self.name = format!("{} (promoted {:?})", self.name, piece);
+        self.piece = piece;
+    }
 }
 
 // Better display for print statements
@@ -57,7 +104,11 @@ impl fmt::Display for ChessPiece {
         write!(
             f,
             "{:?} {:?} at {:?}        -        {} {}",
-            self.color, self.piece, self.position, self.name, self.value,
+            self.color,
+            self.piece,
+            self.position,
+            self.name,
+            self.value(),
         )
     }
 }
@@ -7,6 +7,8 @@ mod systems;
 use components::*;
 use systems::*;
 
+use crate::game::MoveSet;
+
 pub struct PiecesPlugin;
 
 // Registering the components is important so they show up in the inspector
@@ -16,6 +18,12 @@ impl Plugin for PiecesPlugin {
         app.register_type::<ChessPiece>()
             .register_type::<PieceType>()
             .register_type::<PieceColor>()
-            .add_systems(Startup, set_up_game);
+            .register_type::<ChessBoard>()
+            .register_type::<BoardSquare>()
+            .register_type::<BoardImage>()
+            .add_systems(Startup, set_up_game)
+            .add_systems(Update, reset_pieces.before(MoveSet))
+            .add_systems(Update, sync_piece_sprites.after(MoveSet))
+            .add_systems(Update, apply_board_layout.before(MoveSet));
     }
 }
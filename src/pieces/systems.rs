@@ -2,32 +2,231 @@ use bevy::prelude::*;
 
 use crate::pieces::components::*;
 
-use crate::game::resources::BOARD_OFFSET;
-use crate::game::resources::TILE_SIZE;
+use crate::engine::board::{Board, tile};
+use crate::game::components::PieceAnimation;
+use crate::game::resources::{BoardLayout, FRAME_Z, SQUARE_Z};
+use crate::game::resources::{JumpToPosition, NewGame};
+use crate::theme::resources::{Theme, Themes};
+
+// Inside the frame the board image sits between the squares and the highlights
+const BOARD_IMAGE_Z: f32 = 0.12;
+
+// The frame and squares are 1x1 sprites scaled up to the layout's size
+pub fn set_up_game(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    layout: Res<BoardLayout>,
+    themes: Res<Themes>,
+) {
+    let theme = themes.current();
+
+    // the frame isn't scaled in z so the board image keeps its z offset
+    commands
+        .spawn((
+            Sprite::from_color(theme.frame_color(), Vec2::ONE),
+            Transform {
+                translation: Vec3::new(0.0, 0.0, FRAME_Z),
+                scale: frame_scale(&layout),
+                ..Default::default()
+            },
+            ChessBoard,
+        ))
+        .with_child((
+            Sprite {
+                image: match &theme.board_image {
+                    Some(path) => asset_server.load(path),
+                    None => Handle::default(),
+                },
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            },
+            // 8 of the 10 tiles of the frame
+            Transform {
+                translation: Vec3::new(0.0, 0.0, BOARD_IMAGE_Z),
+                scale: Vec3::new(0.8, 0.8, 1.0),
+                ..Default::default()
+            },
+            if theme.board_image.is_some() {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            },
+            BoardImage,
+        ));
 
-pub fn set_up_game(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn((
-        Sprite::from_image(asset_server.load("images/board.png")),
-        Transform::from_xyz(450.0, 450.0, -0.1),
-        GlobalTransform::default(),
-    ));
+    for file in 1..=8 {
+        for rank in 1..=8 {
+            let square = BoardSquare((file, rank));
+            let color = theme.square_color(square.is_dark());
+
+            commands.spawn((
+                Sprite::from_color(color, Vec2::ONE),
+                Transform {
+                    translation: layout.tile_to_world((file, rank)).extend(SQUARE_Z),
+                    scale: Vec3::splat(layout.tile_size),
+                    ..Default::default()
+                },
+                square,
+            ));
+        }
+    }
+
+    spawn_pieces(&mut commands, &asset_server, &layout, theme);
+}
+
+fn frame_scale(layout: &BoardLayout) -> Vec3 {
+    Vec3::new(layout.frame_size(), layout.frame_size(), 1.0)
+}
+
+// Window got resized, so the board and everything on it gets scaled and moved to fit again
+pub fn apply_board_layout(
+    layout: Res<BoardLayout>,
+    mut q_board: Query<&mut Transform, (With<ChessBoard>, Without<BoardSquare>)>,
+    mut q_squares: Query<(&BoardSquare, &mut Transform), Without<ChessPiece>>,
+    mut q_pieces: Query<(Entity, &ChessPiece, &mut Transform), Without<ChessBoard>>,
+    mut commands: Commands,
+) {
+    if !layout.is_changed() {
+        return;
+    }
+
+    for mut transform in q_board.iter_mut() {
+        transform.scale = frame_scale(&layout);
+    }
+
+    for (square, mut transform) in q_squares.iter_mut() {
+        transform.translation = layout.tile_to_world(square.0).extend(SQUARE_Z);
+        transform.scale = Vec3::splat(layout.tile_size);
+    }
+
+    // pieces that were still sliding would end up where the old layout had the tile
+    for (entity, piece, mut transform) in q_pieces.iter_mut() {
+        let position = layout.tile_to_world(piece.position);
+
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.scale = Vec3::splat(layout.piece_scale());
+
+        commands.entity(entity).remove::<PieceAnimation>();
+    }
+}
+
+// Starting a new game (or jumping to another position) throws every piece away and sets them all up again
+pub fn reset_pieces(
+    mut events: EventReader<NewGame>,
+    mut jumps: EventReader<JumpToPosition>,
+    query: Query<Entity, With<ChessPiece>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    layout: Res<BoardLayout>,
+    themes: Res<Themes>,
+) {
+    let Some(board) = events
+        .read()
+        .map(|e| &e.board)
+        .chain(jumps.read().map(|e| &e.board))
+        .last()
+    else {
+        return;
+    };
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if *board == Board::start() {
+        spawn_pieces(&mut commands, &asset_server, &layout, themes.current());
+    } else {
+        spawn_board(
+            &mut commands,
+            &asset_server,
+            &layout,
+            themes.current(),
+            board,
+        );
+    }
+}
+
+// Keeps the sprites in line with the pieces, right now that's only needed for promotions
+pub fn sync_piece_sprites(
+    mut query: Query<(&ChessPiece, &mut Sprite), Changed<ChessPiece>>,
+    asset_server: Res<AssetServer>,
+    themes: Res<Themes>,
+) {
+    for (piece, mut sprite) in query.iter_mut() {
+        // loading the same path again just gives back the same handle
+        let image = asset_server.load(themes.current().piece_image(piece.piece, piece.color));
+
+        if sprite.image != image {
+            sprite.image = image;
+        }
+    }
+}
+
+// For games that don't start from the normal position (FEN or chess960)
+fn spawn_board(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+    board: &Board,
+) {
+    for (square, piece) in board.pieces() {
+        let (file, rank) = tile(square);
+
+        // numbered like the normal setup, "Knight 1", "Knight 2" and so on
+        let number = board
+            .pieces()
+            .filter(|(s, p)| *s < square && *p == piece)
+            .count()
+            + 1;
+
+        let chess_piece = ChessPiece::new(
+            &format!("{:?} {}", piece.kind, number),
+            piece.kind,
+            piece.color,
+            (file, rank),
+        );
+
+        commands.spawn((
+            Sprite::from_image(asset_server.load(theme.piece_image(piece.kind, piece.color))),
+            Transform {
+                translation: layout.tile_to_world((file, rank)).extend(0.0),
+                scale: Vec3::splat(layout.piece_scale()),
+                ..Default::default()
+            },
+            chess_piece,
+        ));
+    }
+}
 
+fn spawn_pieces(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // 6 types of pieces
 
     for i in 0..6 {
         match i {
-            0 => spawn_pawns(&mut commands, &asset_server),
-            1 => spawn_knights(&mut commands, &asset_server),
-            2 => spawn_rooks(&mut commands, &asset_server),
-            3 => spawn_bishops(&mut commands, &asset_server),
-            4 => spawn_queens(&mut commands, &asset_server),
-            5 => spawn_kings(&mut commands, &asset_server),
+            0 => spawn_pawns(commands, asset_server, layout, theme),
+            1 => spawn_knights(commands, asset_server, layout, theme),
+            2 => spawn_rooks(commands, asset_server, layout, theme),
+            3 => spawn_bishops(commands, asset_server, layout, theme),
+            4 => spawn_queens(commands, asset_server, layout, theme),
+            5 => spawn_kings(commands, asset_server, layout, theme),
             _ => println!("{} idk too much of a number.", i),
         }
     }
 }
 
-fn spawn_pawns(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn spawn_pawns(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // Could do a nested loop for both colors
     // but this is easier to read and understand
 
@@ -35,17 +234,15 @@ fn spawn_pawns(commands: &mut Commands, asset_server: &Res<AssetServer>) {
         // Start at 1 or else it would multiply by 0
         // Also for the name so the names start at 1 not 0
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (white_pawn as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (2 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((white_pawn, 2)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/w_Pawn.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Pawn, PieceColor::White)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -53,23 +250,20 @@ fn spawn_pawns(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Pawn,
                 PieceColor::White,
                 (white_pawn, 2),
-                1,
             ),
         ));
     }
 
     for black_pawn in 1..9 {
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (black_pawn as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (7 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((black_pawn, 7)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/b_Pawn.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Pawn, PieceColor::Black)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -77,29 +271,31 @@ fn spawn_pawns(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Pawn,
                 PieceColor::Black,
                 (black_pawn, 7),
-                1,
             ),
         ));
     }
 }
 
-fn spawn_knights(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn spawn_knights(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // See "spawn_pawns" for explanation
 
     for white_knight in 1..3 {
         let pos_x: u8 = if white_knight == 1 { 2 } else { 7 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (1 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 1)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/w_Knight.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Knight, PieceColor::White)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -107,7 +303,6 @@ fn spawn_knights(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Knight,
                 PieceColor::White,
                 (pos_x, 1),
-                3,
             ),
         ));
     }
@@ -115,17 +310,15 @@ fn spawn_knights(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     for black_knight in 1..3 {
         let pos_x: u8 = if black_knight == 1 { 2 } else { 7 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (8 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 8)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/b_Knight.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Knight, PieceColor::Black)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -133,29 +326,31 @@ fn spawn_knights(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Knight,
                 PieceColor::Black,
                 (pos_x, 8),
-                3,
             ),
         ));
     }
 }
 
-fn spawn_rooks(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn spawn_rooks(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // See "spawn_pawns" for explanation
 
     for white_rook in 1..3 {
         let pos_x: u8 = if white_rook == 1 { 1 } else { 8 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (1 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 1)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/w_Rook.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Rook, PieceColor::White)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -163,7 +358,6 @@ fn spawn_rooks(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Rook,
                 PieceColor::White,
                 (pos_x, 1),
-                5,
             ),
         ));
     }
@@ -171,17 +365,15 @@ fn spawn_rooks(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     for black_rook in 1..3 {
         let pos_x: u8 = if black_rook == 1 { 1 } else { 8 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (8 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 8)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/b_Rook.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Rook, PieceColor::Black)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -189,29 +381,31 @@ fn spawn_rooks(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Rook,
                 PieceColor::Black,
                 (pos_x, 8),
-                5,
             ),
         ));
     }
 }
 
-fn spawn_bishops(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn spawn_bishops(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // See "spawn_pawns" for explanation
 
     for white_bishop in 1..3 {
         let pos_x: u8 = if white_bishop == 1 { 3 } else { 6 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (1 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 1)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/w_Bishop.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Bishop, PieceColor::White)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -219,7 +413,6 @@ fn spawn_bishops(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Bishop,
                 PieceColor::White,
                 (pos_x, 1),
-                3,
             ),
         ));
     }
@@ -227,17 +420,15 @@ fn spawn_bishops(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     for black_bishop in 1..3 {
         let pos_x: u8 = if black_bishop == 1 { 3 } else { 6 };
 
-        let pos = Vec3::new(
-            BOARD_OFFSET.x + (pos_x as f32 - 1.0) * TILE_SIZE,
-            BOARD_OFFSET.y + (8 as f32 - 1.0) * TILE_SIZE,
-            0.0,
-        );
+        let pos = layout.tile_to_world((pos_x, 8)).extend(0.0);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("images/b_Bishop.png")),
+            Sprite::from_image(
+                asset_server.load(theme.piece_image(PieceType::Bishop, PieceColor::Black)),
+            ),
             Transform {
                 translation: pos,
-                scale: Vec3::splat(0.7),
+                scale: Vec3::splat(layout.piece_scale()),
                 ..Default::default()
             },
             ChessPiece::new(
@@ -245,78 +436,79 @@ fn spawn_bishops(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 PieceType::Bishop,
                 PieceColor::Black,
                 (pos_x, 8),
-                3,
             ),
         ));
     }
 }
 
-fn spawn_queens(commands: &mut Commands, asset_server: &Res<AssetServer>) {
-    let pos = Vec3::new(
-        BOARD_OFFSET.x + (4 as f32 - 1.0) * TILE_SIZE,
-        BOARD_OFFSET.y + (1 as f32 - 1.0) * TILE_SIZE,
-        0.0,
-    );
+fn spawn_queens(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
+    let pos = layout.tile_to_world((4, 1)).extend(0.0);
 
     commands.spawn((
-        Sprite::from_image(asset_server.load("images/w_Queen.png")),
+        Sprite::from_image(
+            asset_server.load(theme.piece_image(PieceType::Queen, PieceColor::White)),
+        ),
         Transform {
             translation: pos,
-            scale: Vec3::splat(0.7),
+            scale: Vec3::splat(layout.piece_scale()),
             ..Default::default()
         },
-        ChessPiece::new("Queen", PieceType::Queen, PieceColor::White, (4, 1), 9),
+        ChessPiece::new("Queen", PieceType::Queen, PieceColor::White, (4, 1)),
     ));
 
-    let pos = Vec3::new(
-        BOARD_OFFSET.x + (4 as f32 - 1.0) * TILE_SIZE,
-        BOARD_OFFSET.y + (8 as f32 - 1.0) * TILE_SIZE,
-        0.0,
-    );
+    let pos = layout.tile_to_world((4, 8)).extend(0.0);
 
     commands.spawn((
-        Sprite::from_image(asset_server.load("images/b_Queen.png")),
+        Sprite::from_image(
+            asset_server.load(theme.piece_image(PieceType::Queen, PieceColor::Black)),
+        ),
         Transform {
             translation: pos,
-            scale: Vec3::splat(0.7),
+            scale: Vec3::splat(layout.piece_scale()),
             ..Default::default()
         },
-        ChessPiece::new("Queen", PieceType::Queen, PieceColor::Black, (4, 8), 9),
+        ChessPiece::new("Queen", PieceType::Queen, PieceColor::Black, (4, 8)),
     ));
 }
 
-fn spawn_kings(commands: &mut Commands, asset_server: &Res<AssetServer>) {
+fn spawn_kings(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    layout: &BoardLayout,
+    theme: &Theme,
+) {
     // Value of the king is 0 since it cannot be captured
 
-    let pos = Vec3::new(
-        BOARD_OFFSET.x + (5 as f32 - 1.0) * TILE_SIZE,
-        BOARD_OFFSET.y + (1 as f32 - 1.0) * TILE_SIZE,
-        0.0,
-    );
+    let pos = layout.tile_to_world((5, 1)).extend(0.0);
 
     commands.spawn((
-        Sprite::from_image(asset_server.load("images/w_King.png")),
+        Sprite::from_image(
+            asset_server.load(theme.piece_image(PieceType::King, PieceColor::White)),
+        ),
         Transform {
             translation: pos,
-            scale: Vec3::splat(0.7),
+            scale: Vec3::splat(layout.piece_scale()),
             ..Default::default()
         },
-        ChessPiece::new("King", PieceType::King, PieceColor::White, (5, 1), 0),
+        ChessPiece::new("King", PieceType::King, PieceColor::White, (5, 1)),
     ));
 
-    let pos = Vec3::new(
-        BOARD_OFFSET.x + (5 as f32 - 1.0) * TILE_SIZE,
-        BOARD_OFFSET.y + (8 as f32 - 1.0) * TILE_SIZE,
-        0.0,
-    );
+    let pos = layout.tile_to_world((5, 8)).extend(0.0);
 
     commands.spawn((
-        Sprite::from_image(asset_server.load("images/b_King.png")),
+        Sprite::from_image(
+            asset_server.load(theme.piece_image(PieceType::King, PieceColor::Black)),
+        ),
         Transform {
             translation: pos,
-            scale: Vec3::splat(0.7),
+            scale: Vec3::splat(layout.piece_scale()),
             ..Default::default()
         },
-        ChessPiece::new("King", PieceType::King, PieceColor::Black, (5, 8), 0),
+        ChessPiece::new("King", PieceType::King, PieceColor::Black, (5, 8)),
     ));
 }
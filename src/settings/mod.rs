@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy_egui::EguiContextPass;
+
+pub mod resources;
+pub mod systems;
+
+use resources::*;
+use systems::*;
+
+pub struct SettingsPlugin;
+
+// The Settings resource itself is loaded in main.rs, the window size is needed before the app exists.
+// Everything else is handed to the other plugins' resources before the board gets set up
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Settings>()
+            .register_type::<SettingsWindow>()
+            .insert_resource(SettingsWindow { open: false })
+            .add_event::<ResetSettings>()
+            .add_systems(PreStartup, apply_settings)
+            .add_systems(
+                Update,
+                (
+                    reset_settings,
+                    apply_settings.run_if(on_event::<ResetSettings>),
+                    remember_window_size,
+                    save_settings,
+                )
+                    .chain(),
+            )
+            .add_systems(EguiContextPass, settings_ui);
+    }
+}
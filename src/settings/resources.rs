@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+
+use crate::{
+    clock::resources::{IncrementMode, TimeControl, TimeControlPreset},
+    engine::search::max_threads,
+    game::resources::CoordinateStyle,
+};
+
+const APP_FOLDER: &str = "chess-in-rust";
+const SETTINGS_FILE: &str = "settings.ron";
+
+// Settings are written this long after the last change, so dragging a slider doesn't write every frame
+pub const SAVE_DELAY: f32 = 0.5;
+
+// How long the animations take at speed 1
+pub const MOVE_DURATION: f32 = 0.2;
+pub const CAPTURE_DURATION: f32 = 0.25;
+
+// Everything that's remembered between runs. Most of it lives in other resources while
+// the game runs (Themes, SoundSettings, GameSetup...), this is what gets written to the file.
+// Fields missing in the file get their default, so older files keep working
+#[derive(Resource, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+#[serde(default)]
+pub struct Settings {
+    pub window_width: f32,
+    pub window_height: f32,
+    // Escape opens the world inspector
+    pub inspector: bool,
+    pub theme: String,
+    pub volume: f32,
+    pub muted: bool,
+    pub auto_flip: bool,
+    // board seen from black's side
+    pub flipped: bool,
+    pub coordinates: CoordinateStyle,
+    // what the new game screen starts with
+    pub time_control: Option<TimeControl>,
+    pub computer_depth: u8,
+    // how many threads the computer searches with
+    pub threads: usize,
+    // UCI engine to use instead of the built-in one, empty for the built-in one
+    pub engine_path: String,
+    // evaluation bar next to the board
+    pub eval_bar: bool,
+    // 1 is normal, 2 twice as fast
+    pub animation_speed: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_width: 1280.0,
+            window_height: 900.0,
+            inspector: true,
+            theme: "Classic".to_string(),
+            volume: 0.7,
+            muted: false,
+            auto_flip: false,
+            flipped: false,
+            coordinates: CoordinateStyle::Edges,
+            time_control: Some(TimeControlPreset::Rapid.time_control(IncrementMode::Fischer)),
+            computer_depth: 3,
+            // one core is left for drawing the board
+            threads: (max_threads() - 1).max(1),
+            engine_path: String::new(),
+            eval_bar: true,
+            animation_speed: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    // settings.ron in the config folder, None when there's no home folder at all
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(APP_FOLDER).join(SETTINGS_FILE))
+    }
+
+    // A missing file gives the defaults. A broken one too, it gets copied to
+    // settings.ron.bak first so nothing is lost when the defaults get saved over it
+    pub fn load() -> Settings {
+        let Some(path) = Settings::path() else {
+            return Settings::default();
+        };
+
+        let Ok(text) = fs::read_to_string(&path) else {
+            println!("No settings at {}, using the defaults", path.display());
+            return Settings::default();
+        };
+
+        match ron::from_str::<Settings>(&text) {
+            Ok(settings) => settings.sanitized(),
+            Err(error) => {
+                println!("Couldn't read {}: {}", path.display(), error);
+
+                if let Err(error) = fs::copy(&path, path.with_extension("ron.bak")) {
+                    println!("Couldn't back up the broken settings: {}", error);
+                }
+
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<PathBuf, String> {
+        let path = Settings::path().ok_or("No config folder")?;
+
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        }
+
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| e.to_string())?;
+
+        Ok(path)
+    }
+
+    // Hand edited files can have anything in them
+    fn sanitized(mut self) -> Settings {
+        self.window_width = self.window_width.max(640.0);
+        self.window_height = self.window_height.max(480.0);
+        self.volume = self.volume.clamp(0.0, 1.0);
+        self.computer_depth = self.computer_depth.clamp(1, 8);
+        self.threads = self.threads.clamp(1, max_threads());
+        self.animation_speed = self.animation_speed.clamp(0.25, 4.0);
+
+        // a clock without time can't be played, a broken increment is just left out
+        if let Some(time_control) = &mut self.time_control {
+            if !time_control.base.is_finite() || time_control.base <= 0.0 {
+                time_control.base = TimeControlPreset::Rapid
+                    .time_control(time_control.mode)
+                    .base;
+            }
+
+            if !time_control.increment.is_finite() || time_control.increment < 0.0 {
+                time_control.increment = 0.0;
+            }
+        }
+
+        self
+    }
+}
+
+// Where config files go on each system, same places the dirs crate would use
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }
+}
+
+// The settings window can be opened from the main menu and next to the board
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct SettingsWindow {
+    pub open: bool,
+}
+
+// Puts everything back to the defaults
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct ResetSettings;
@@ -0,0 +1,296 @@
+use bevy::{prelude::*, window::WindowResized};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    engine::search::max_threads,
+    game::resources::{
+        AnimationSettings, BoardOrientation, CoordinateLabels, CoordinateStyle, GameSetup,
+    },
+    settings::resources::*,
+    sound::resources::SoundSettings,
+    theme::resources::Themes,
+};
+
+// Hands the settings from the file (or the defaults after a reset) to the resources the game uses
+pub fn apply_settings(
+    settings: Res<Settings>,
+    mut themes: ResMut<Themes>,
+    mut sound: ResMut<SoundSettings>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut labels: ResMut<CoordinateLabels>,
+    mut setup: ResMut<GameSetup>,
+    mut animation: ResMut<AnimationSettings>,
+) {
+    // a theme that isn't there anymore just keeps the one that was picked at startup
+    if let Some(i) = themes.all.iter().position(|t| t.name == settings.theme) {
+        themes.selected = i;
+    }
+
+    sound.volume = settings.volume;
+    sound.muted = settings.muted;
+    orientation.auto_flip = settings.auto_flip;
+    orientation.flipped = settings.flipped;
+    labels.style = settings.coordinates;
+    setup.time_control = settings.time_control;
+    setup.computer_depth = settings.computer_depth;
+    animation.move_duration = MOVE_DURATION / settings.animation_speed;
+    animation.capture_duration = CAPTURE_DURATION / settings.animation_speed;
+}
+
+pub fn reset_settings(mut events: EventReader<ResetSettings>, mut settings: ResMut<Settings>) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    // the window keeps the size it has right now
+    *settings = Settings {
+        window_width: settings.window_width,
+        window_height: settings.window_height,
+        ..Settings::default()
+    };
+}
+
+pub fn remember_window_size(
+    mut events: EventReader<WindowResized>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(resized) = events.read().last() else {
+        return;
+    };
+
+    if settings.window_width != resized.width || settings.window_height != resized.height {
+        settings.window_width = resized.width;
+        settings.window_height = resized.height;
+    }
+}
+
+// Collects the settings from wherever they were changed (keys, side panel, new game screen,
+// settings window) and writes the file once they stopped changing for SAVE_DELAY,
+// or right away when the app is closing
+#[allow(clippy::too_many_arguments)]
+pub fn save_settings(
+    mut settings: ResMut<Settings>,
+    themes: Res<Themes>,
+    sound: Res<SoundSettings>,
+    orientation: Res<BoardOrientation>,
+    labels: Res<CoordinateLabels>,
+    setup: Res<GameSetup>,
+    animation: Res<AnimationSettings>,
+    time: Res<Time>,
+    mut exit_events: EventReader<AppExit>,
+    mut last_saved: Local<Option<Settings>>,
+    mut last_change: Local<f32>,
+) {
+    let current = Settings {
+        theme: themes.current().name.clone(),
+        volume: sound.volume,
+        muted: sound.muted,
+        auto_flip: orientation.auto_flip,
+        flipped: orientation.flipped,
+        coordinates: labels.style,
+        time_control: setup.time_control,
+        computer_depth: setup.computer_depth,
+        animation_speed: MOVE_DURATION / animation.move_duration,
+        ..settings.clone()
+    };
+
+    // the first time is right after loading, so there's nothing new to write
+    let Some(saved) = last_saved.as_ref() else {
+        *last_saved = Some(current);
+        return;
+    };
+
+    if *settings != current {
+        *settings = current.clone();
+    }
+
+    // also catches the settings window changing Settings directly
+    if settings.is_changed() {
+        *last_change = time.elapsed_secs();
+    }
+
+    let exiting = exit_events.read().count() > 0;
+    let settled = time.elapsed_secs() - *last_change >= SAVE_DELAY;
+
+    if *saved == current || !(settled || exiting) {
+        return;
+    }
+
+    match current.save() {
+        Ok(path) => println!("Saved the settings to {}", path.display()),
+        Err(error) => println!("Couldn't save the settings: {}", error),
+    }
+
+    *last_saved = Some(current);
+}
+
+// Run condition for the world inspector
+pub fn inspector_enabled(settings: Res<Settings>) -> bool {
+    settings.inspector
+}
+
+// Everything is changed on copies and only written back when it's different,
+// otherwise change detection would redo the labels / theme every frame the window is open
+#[allow(clippy::too_many_arguments)]
+pub fn settings_ui(
+    mut contexts: EguiContexts,
+    mut window: ResMut<SettingsWindow>,
+    mut settings: ResMut<Settings>,
+    mut themes: ResMut<Themes>,
+    mut sound: ResMut<SoundSettings>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut labels: ResMut<CoordinateLabels>,
+    mut animation: ResMut<AnimationSettings>,
+    mut reset: EventWriter<ResetSettings>,
+) {
+    if !window.open {
+        return;
+    }
+
+    let ctx = contexts.ctx_mut();
+
+    let mut open = true;
+    let mut close = false;
+
+    let mut selected = themes.selected;
+    let mut style = labels.style;
+    let mut auto_flip = orientation.auto_flip;
+    let mut speed = MOVE_DURATION / animation.move_duration;
+    let mut volume = sound.volume;
+    let mut muted = sound.muted;
+    let mut inspector = settings.inspector;
+    let mut engine_path = settings.engine_path.clone();
+    let mut threads = settings.threads;
+    let mut eval_bar = settings.eval_bar;
+
+    egui::Window::new("Settings")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            egui::Grid::new("settings")
+                .num_columns(2)
+                .spacing([30.0, 10.0])
+                .show(ui, |ui| {
+                    ui.label("Theme");
+                    egui::ComboBox::from_id_salt("theme")
+                        .selected_text(&themes.current().name)
+                        .show_ui(ui, |ui| {
+                            for (i, theme) in themes.all.iter().enumerate() {
+                                ui.selectable_value(&mut selected, i, &theme.name);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Coordinates");
+                    egui::ComboBox::from_id_salt("coordinates")
+                        .selected_text(format!("{:?}", style))
+                        .show_ui(ui, |ui| {
+                            for option in [
+                                CoordinateStyle::Hidden,
+                                CoordinateStyle::Edges,
+                                CoordinateStyle::InsideSquares,
+                            ] {
+                                ui.selectable_value(&mut style, option, format!("{:?}", option));
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Board");
+                    ui.checkbox(&mut auto_flip, "Turn towards the side to move");
+                    ui.end_row();
+
+                    ui.label("Animation speed");
+                    ui.add(egui::Slider::new(&mut speed, 0.25..=4.0).logarithmic(true));
+                    ui.end_row();
+
+                    ui.label("Sound");
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut muted, "Mute");
+                        ui.add(egui::Slider::new(&mut volume, 0.0..=1.0).show_value(false));
+                    });
+                    ui.end_row();
+
+                    ui.label("Engine");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut engine_path)
+                            .hint_text("built-in, or the path to a UCI engine"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Threads");
+                    ui.add(egui::Slider::new(&mut threads, 1..=max_threads()))
+                        .on_hover_text("How many cores the computer player thinks with");
+                    ui.end_row();
+
+                    ui.label("Evaluation");
+                    ui.checkbox(&mut eval_bar, "Show the evaluation bar");
+                    ui.end_row();
+
+                    ui.label("Inspector");
+                    ui.checkbox(&mut inspector, "Escape opens the inspector");
+                    ui.end_row();
+                });
+
+            ui.add_space(6.0);
+            ui.label("The time control and computer level are remembered from the new game screen, the window size when it's resized.");
+
+            if let Some(path) = Settings::path() {
+                ui.small(format!("Saved in {}", path.display()));
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Reset to defaults").clicked() {
+                    reset.write(ResetSettings);
+                }
+
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if selected != themes.selected {
+        themes.selected = selected;
+    }
+
+    if style != labels.style {
+        labels.style = style;
+    }
+
+    if auto_flip != orientation.auto_flip {
+        orientation.auto_flip = auto_flip;
+    }
+
+    if speed != MOVE_DURATION / animation.move_duration {
+        animation.move_duration = MOVE_DURATION / speed;
+        animation.capture_duration = CAPTURE_DURATION / speed;
+    }
+
+    if volume != sound.volume || muted != sound.muted {
+        sound.volume = volume;
+        sound.muted = muted;
+    }
+
+    if inspector != settings.inspector {
+        settings.inspector = inspector;
+    }
+
+    if engine_path != settings.engine_path {
+        settings.engine_path = engine_path;
+    }
+
+    if threads != settings.threads {
+        settings.threads = threads;
+    }
+
+    if eval_bar != settings.eval_bar {
+        settings.eval_bar = eval_bar;
+    }
+
+    if !open || close {
+        window.open = false;
+    }
+}
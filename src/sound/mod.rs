@@ -0,0 +1,39 @@
+use bevy::{audio::AddAudioSource, prelude::*};
+
+pub mod resources;
+pub mod synth;
+mod systems;
+
+use resources::*;
+use synth::*;
+use systems::*;
+
+use crate::{game::MoveSet, states::AppState};
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SoundSettings>()
+            .register_type::<SoundEffect>()
+            .register_type::<Tone>()
+            .add_audio_source::<Synth>()
+            .insert_resource(SoundSettings {
+                volume: 0.7,
+                muted: false,
+            })
+            .init_resource::<Sounds>()
+            .add_event::<PlaySound>()
+            .add_systems(Startup, set_up_sounds)
+            .add_systems(
+                Update,
+                (
+                    (move_sounds, illegal_move_sounds).after(MoveSet),
+                    low_time_sound.run_if(in_state(AppState::Playing)),
+                    mute_input,
+                ),
+            )
+            .add_systems(OnEnter(AppState::GameOver), game_end_sound)
+            .add_systems(PostUpdate, play_sounds);
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::sound::synth::{Synth, Tone};
+
+// The low time warning goes off once when a clock drops below this (in seconds)
+pub const LOW_TIME: f32 = 10.0;
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Castle,
+    Check,
+    Promotion,
+    Illegal,
+    LowTime,
+    GameEnd,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 8] = [
+        SoundEffect::Move,
+        SoundEffect::Capture,
+        SoundEffect::Castle,
+        SoundEffect::Check,
+        SoundEffect::Promotion,
+        SoundEffect::Illegal,
+        SoundEffect::LowTime,
+        SoundEffect::GameEnd,
+    ];
+
+    pub fn synth(&self) -> Synth {
+        let tones = match self {
+            SoundEffect::Move => vec![Tone::knock(180.0, 120.0, 0.07, 0.6)],
+            SoundEffect::Capture => vec![Tone::knock(300.0, 80.0, 0.12, 0.9)],
+            // king and rook
+            SoundEffect::Castle => vec![
+                Tone::knock(180.0, 120.0, 0.07, 0.6),
+                Tone::silence(0.04),
+                Tone::knock(160.0, 110.0, 0.07, 0.6),
+            ],
+            SoundEffect::Check => vec![Tone::note(660.0, 0.09), Tone::note(880.0, 0.14)],
+            SoundEffect::Promotion => vec![
+                Tone::note(523.0, 0.07),
+                Tone::note(659.0, 0.07),
+                Tone::note(784.0, 0.07),
+                Tone::note(1047.0, 0.15),
+            ],
+            SoundEffect::Illegal => vec![Tone {
+                noise: 0.2,
+                ..Tone::note(150.0, 0.18)
+            }],
+            SoundEffect::LowTime => vec![
+                Tone::note(1000.0, 0.05),
+                Tone::silence(0.07),
+                Tone::note(1000.0, 0.05),
+                Tone::silence(0.07),
+                Tone::note(1000.0, 0.05),
+            ],
+            SoundEffect::GameEnd => vec![
+                Tone::note(784.0, 0.15),
+                Tone::note(659.0, 0.15),
+                Tone::note(523.0, 0.3),
+            ],
+        };
+
+        Synth { tones }
+    }
+}
+
+// volume goes from 0 to 1
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct SoundSettings {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+// The synths for every sound, added to the assets once at startup
+#[derive(Resource, Default)]
+pub struct Sounds(pub HashMap<SoundEffect, Handle<Synth>>);
+
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct PlaySound(pub SoundEffect);
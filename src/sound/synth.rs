@@ -0,0 +1,135 @@
+use bevy::{
+    audio::{Decodable, Source},
+    prelude::*,
+};
+use std::{f32::consts::TAU, time::Duration};
+
+// There are no sound files, every sound is put together from a few short tones
+const SAMPLE_RATE: u32 = 44_100;
+
+// Frequencies are in Hz, the duration in seconds.
+// noise mixes in white noise, 0 is a clean sine and 1 only noise (sounds like a knock)
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct Tone {
+    pub start_frequency: f32,
+    pub end_frequency: f32,
+    pub duration: f32,
+    pub volume: f32,
+    pub noise: f32,
+}
+
+impl Tone {
+    pub fn note(frequency: f32, duration: f32) -> Tone {
+        Tone {
+            start_frequency: frequency,
+            end_frequency: frequency,
+            duration,
+            volume: 0.5,
+            noise: 0.0,
+        }
+    }
+
+    pub fn knock(start_frequency: f32, end_frequency: f32, duration: f32, volume: f32) -> Tone {
+        Tone {
+            start_frequency,
+            end_frequency,
+            duration,
+            volume,
+            noise: 0.6,
+        }
+    }
+
+    pub fn silence(duration: f32) -> Tone {
+        Tone {
+            volume: 0.0,
+            ..Tone::note(0.0, duration)
+        }
+    }
+}
+
+// The tones are played one after another
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct Synth {
+    pub tones: Vec<Tone>,
+}
+
+impl Synth {
+    fn render(&self) -> Vec<f32> {
+        let mut samples = Vec::new();
+        // xorshift, the noise doesn't need to be anything special
+        let mut seed: u32 = 0x9E37_79B9;
+
+        for tone in self.tones.iter() {
+            let count = (tone.duration * SAMPLE_RATE as f32) as usize;
+            let attack = 0.004 * SAMPLE_RATE as f32;
+            let mut phase = 0.0;
+
+            for i in 0..count {
+                let t = i as f32 / count as f32;
+                let frequency =
+                    tone.start_frequency + (tone.end_frequency - tone.start_frequency) * t;
+                phase = (phase + frequency / SAMPLE_RATE as f32 * TAU) % TAU;
+
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let noise = seed as f32 / u32::MAX as f32 * 2.0 - 1.0;
+
+                let wave = phase.sin() * (1.0 - tone.noise) + noise * tone.noise;
+                // fade in quickly and out slowly so there's no click at either end
+                let envelope = (i as f32 / attack).min(1.0) * (1.0 - t).powi(2);
+
+                samples.push(wave * envelope * tone.volume);
+            }
+        }
+
+        samples
+    }
+}
+
+pub struct SynthDecoder {
+    samples: Vec<f32>,
+    position: usize,
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.samples.len() as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+impl Decodable for Synth {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SynthDecoder {
+            samples: self.render(),
+            position: 0,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{
+    clock::resources::ChessClock,
+    game::resources::{GameState, IllegalMove, MoveHistory, MoveInput, MoveMade, NewGame},
+    sound::{resources::*, synth::Synth},
+};
+
+pub fn set_up_sounds(mut sounds: ResMut<Sounds>, mut synths: ResMut<Assets<Synth>>) {
+    for effect in SoundEffect::ALL {
+        sounds.0.insert(effect, synths.add(effect.synth()));
+    }
+}
+
+pub fn play_sounds(
+    mut events: EventReader<PlaySound>,
+    sounds: Res<Sounds>,
+    settings: Res<SoundSettings>,
+    mut commands: Commands,
+) {
+    for PlaySound(effect) in events.read() {
+        if settings.muted {
+            continue;
+        }
+
+        let Some(handle) = sounds.0.get(effect) else {
+            continue;
+        };
+
+        commands.spawn((
+            AudioPlayer(handle.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(settings.volume)),
+        ));
+    }
+}
+
+// One sound per move, the most important one wins.
+// Runs after record_move so the history's last move is the one that was just made
+pub fn move_sounds(
+    mut events: EventReader<MoveMade>,
+    history: Res<MoveHistory>,
+    mut play: EventWriter<PlaySound>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let Some(record) = history.last_move() else {
+        return;
+    };
+
+    let effect = if record.san.ends_with('+') || record.san.ends_with('#') {
+        SoundEffect::Check
+    } else if record.san.contains('=') {
+        SoundEffect::Promotion
+    } else if record.san.starts_with("O-O") {
+        SoundEffect::Castle
+    } else if record.captured.is_some() {
+        SoundEffect::Capture
+    } else {
+        SoundEffect::Move
+    };
+
+    play.write(PlaySound(effect));
+}
+
+pub fn illegal_move_sounds(mut events: EventReader<IllegalMove>, mut play: EventWriter<PlaySound>) {
+    if events.read().count() > 0 {
+        play.write(PlaySound(SoundEffect::Illegal));
+    }
+}
+
+// Beeps once when the clock of the side to move goes under LOW_TIME.
+// Each side remembers its own last time, otherwise every move compares against the other clock
+pub fn low_time_sound(
+    clock: Res<ChessClock>,
+    game_state: Res<GameState>,
+    mut new_game_events: EventReader<NewGame>,
+    mut last_remaining: Local<[Option<f32>; 2]>,
+    mut play: EventWriter<PlaySound>,
+) {
+    if new_game_events.read().count() > 0 || !clock.enabled || !clock.running {
+        *last_remaining = [None; 2];
+        return;
+    }
+
+    let remaining = clock.remaining(game_state.turn);
+    let last = &mut last_remaining[game_state.turn as usize];
+
+    if last.is_some_and(|last| last >= LOW_TIME) && remaining < LOW_TIME {
+        play.write(PlaySound(SoundEffect::LowTime));
+    }
+
+    *last = Some(remaining);
+}
+
+pub fn game_end_sound(mut play: EventWriter<PlaySound>) {
+    play.write(PlaySound(SoundEffect::GameEnd));
+}
+
+// M mutes / unmutes
+pub fn mute_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    move_input: Res<MoveInput>,
+    mut settings: ResMut<SoundSettings>,
+) {
+    if move_input.typing || !keys.just_pressed(KeyCode::KeyM) {
+        return;
+    }
+
+    settings.muted = !settings.muted;
+    println!("Sound muted: {}", settings.muted);
+}
@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+
+// Which screen the app is on.
+// The board only takes input while Playing or in Analysis, the clocks only run while Playing
+#[derive(States, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    // picking the players, time control, starting position and variant
+    GameSetup,
+    Playing,
+    // the game over dialog is open
+    GameOver,
+    // going through a finished game (or a free analysis board from the menu)
+    Analysis,
+    // setting up a custom position, the board only listens to the editor
+    Editor,
+}
+
+// Run condition for everything that has to do with the board itself
+pub fn board_active(state: Res<State<AppState>>) -> bool {
+    matches!(
+        state.get(),
+        AppState::Playing | AppState::GameOver | AppState::Analysis
+    )
+}
@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+pub mod resources;
+mod systems;
+
+use resources::*;
+use systems::*;
+
+pub struct ThemePlugin;
+
+// Themes are looked up once when the app starts, switching between them
+// happens by changing Themes.selected
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Themes>()
+            .register_type::<Theme>()
+            .insert_resource(Themes::discover())
+            .add_systems(Update, apply_theme);
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::{asset::io::file::FileAssetReader, prelude::*};
+use serde::Deserialize;
+use std::fs;
+
+use crate::pieces::components::{PieceColor, PieceType};
+
+// Every folder in assets/themes with a theme.ron in it is a theme
+pub const THEMES_FOLDER: &str = "themes";
+pub const THEME_MANIFEST: &str = "theme.ron";
+
+// What's in a theme.ron. Colors are (r, g, b) or (r, g, b, a) in sRGB from 0 to 1.
+// Image paths are relative to the assets folder so themes can share a piece set
+#[derive(Reflect, Deserialize, Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub light: (f32, f32, f32),
+    pub dark: (f32, f32, f32),
+    pub frame: (f32, f32, f32),
+    // drawn over the squares instead of the colors, it should only be the 8x8 squares.
+    // light / dark are still used for the coordinates inside the squares
+    #[serde(default)]
+    pub board_image: Option<String>,
+    // folder with the piece images, named like "w_Pawn.png"
+    pub pieces: String,
+    pub labels: (f32, f32, f32),
+    pub last_move: (f32, f32, f32, f32),
+    pub check: (f32, f32, f32, f32),
+    // older manifests don't have it yet
+    #[serde(default = "default_premove")]
+    pub premove: (f32, f32, f32, f32),
+}
+
+fn default_premove() -> (f32, f32, f32, f32) {
+    (0.2, 0.45, 0.9, 0.45)
+}
+
+// Used when there are no themes in the assets folder, same colors as the classic theme
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "Classic".to_string(),
+            light: (0.99, 1.0, 0.96),
+            dark: (0.45, 0.49, 0.51),
+            frame: (0.28, 0.29, 0.31),
+            board_image: None,
+            pieces: "images".to_string(),
+            labels: (0.95, 0.95, 0.95),
+            last_move: (0.95, 0.85, 0.2, 0.45),
+            check: (0.9, 0.1, 0.1, 0.6),
+            premove: default_premove(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn light_color(&self) -> Color {
+        Color::srgb(self.light.0, self.light.1, self.light.2)
+    }
+
+    pub fn dark_color(&self) -> Color {
+        Color::srgb(self.dark.0, self.dark.1, self.dark.2)
+    }
+
+    pub fn frame_color(&self) -> Color {
+        Color::srgb(self.frame.0, self.frame.1, self.frame.2)
+    }
+
+    pub fn label_color(&self) -> Color {
+        Color::srgb(self.labels.0, self.labels.1, self.labels.2)
+    }
+
+    pub fn last_move_color(&self) -> Color {
+        let (r, g, b, a) = self.last_move;
+        Color::srgba(r, g, b, a)
+    }
+
+    pub fn check_color(&self) -> Color {
+        let (r, g, b, a) = self.check;
+        Color::srgba(r, g, b, a)
+    }
+
+    pub fn premove_color(&self) -> Color {
+        let (r, g, b, a) = self.premove;
+        Color::srgba(r, g, b, a)
+    }
+
+    pub fn square_color(&self, dark: bool) -> Color {
+        if dark {
+            self.dark_color()
+        } else {
+            self.light_color()
+        }
+    }
+
+    // Path of the sprite for a piece in this theme's piece set
+    pub fn piece_image(&self, piece: PieceType, color: PieceColor) -> String {
+        let prefix = match color {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+
+        format!("{}/{}_{:?}.png", self.pieces, prefix, piece)
+    }
+}
+
+// All themes that were found at startup, changing selected swaps the theme right away
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct Themes {
+    pub all: Vec<Theme>,
+    pub selected: usize,
+}
+
+impl Themes {
+    // Looks through assets/themes, broken manifests get printed and skipped
+    pub fn discover() -> Themes {
+        let folder = FileAssetReader::get_base_path()
+            .join("assets")
+            .join(THEMES_FOLDER);
+        let mut all = Vec::new();
+
+        match fs::read_dir(&folder) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let manifest = entry.path().join(THEME_MANIFEST);
+
+                    let Ok(text) = fs::read_to_string(&manifest) else {
+                        continue;
+                    };
+
+                    match ron::from_str::<Theme>(&text) {
+                        Ok(theme) => all.push(theme),
+                        Err(error) => println!("Couldn't load {}: {}", manifest.display(), error),
+                    }
+                }
+            }
+            Err(error) => println!("No themes in {}: {}", folder.display(), error),
+        }
+
+        if all.is_empty() {
+            all.push(Theme::default());
+        }
+
+        all.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // start with the classic look if it's there
+        let selected = all
+            .iter()
+            .position(|theme| theme.name == Theme::default().name)
+            .unwrap_or(0);
+
+        Themes { all, selected }
+    }
+
+    pub fn current(&self) -> &Theme {
+        &self.all[self.selected.min(self.all.len() - 1)]
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::components::{CheckHighlight, LastMoveHighlight, PremoveHighlight},
+    pieces::components::{BoardImage, BoardSquare, ChessBoard, ChessPiece},
+    theme::resources::Themes,
+};
+
+// Everything that's colored by the theme, or has its image from it
+type ThemedSprites<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Sprite,
+        Option<&'static BoardSquare>,
+        Option<&'static ChessPiece>,
+        Has<ChessBoard>,
+        Has<BoardImage>,
+        Has<LastMoveHighlight>,
+        Has<CheckHighlight>,
+        Has<PremoveHighlight>,
+    ),
+>;
+
+// Recolors the board and swaps the sprite handles of everything that's already spawned.
+// Labels, previews and the captured pieces get spawned again by their own systems
+pub fn apply_theme(
+    themes: Res<Themes>,
+    asset_server: Res<AssetServer>,
+    mut q_sprites: ThemedSprites,
+    mut q_board_image: Query<&mut Visibility, With<BoardImage>>,
+) {
+    if !themes.is_changed() {
+        return;
+    }
+
+    let theme = themes.current();
+
+    for (mut sprite, square, piece, frame, board_image, last_move, check, premove) in
+        q_sprites.iter_mut()
+    {
+        if let Some(square) = square {
+            sprite.color = theme.square_color(square.is_dark());
+        } else if let Some(piece) = piece {
+            sprite.image = asset_server.load(theme.piece_image(piece.piece, piece.color));
+        } else if frame {
+            sprite.color = theme.frame_color();
+        } else if board_image {
+            if let Some(path) = &theme.board_image {
+                sprite.image = asset_server.load(path);
+            }
+        } else if last_move {
+            sprite.color = theme.last_move_color();
+        } else if check {
+            sprite.color = theme.check_color();
+        } else if premove {
+            sprite.color = theme.premove_color();
+        }
+    }
+
+    // without an image the squares underneath are shown
+    for mut visibility in q_board_image.iter_mut() {
+        *visibility = if theme.board_image.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
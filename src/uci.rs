@@ -0,0 +1,215 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    engine::{
+        board::Board,
+        notation::{move_to_uci, parse_move},
+        search::{
+            MATE_SCORE, MATE_THRESHOLD, SearchLimits, SearchResult, TABLE_MEGABYTES, max_threads,
+            search_with, time_budget,
+        },
+        transposition::TranspositionTable,
+    },
+    pieces::components::PieceColor,
+};
+
+// Deepest "go" searches when it doesn't say how deep
+const MAX_DEPTH: u8 = 64;
+
+// The built-in engine on its own, talking UCI on stdin / stdout instead of opening the window.
+// Started with the uci argument, so it can play in other GUIs or against other engines
+pub fn run() {
+    let mut board = Board::start();
+    // hashes of the positions before board, from the moves after "position"
+    let mut played: Vec<u64> = Vec::new();
+    let mut threads = 1;
+    // castling as king takes rook, what chess960 GUIs send and expect back
+    let mut chess960 = false;
+    let table = Arc::new(TranspositionTable::new(TABLE_MEGABYTES));
+    let mut running: Option<(Arc<AtomicBool>, JoinHandle<()>)> = None;
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.first().copied() {
+            Some("uci") => {
+                println!("id name Chess in Rust");
+                println!("id author Chess in Rust");
+                println!(
+                    "option name Threads type spin default 1 min 1 max {}",
+                    max_threads()
+                );
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => {
+                if let Some(value) = option_value(&words, "Threads") {
+                    match value.parse::<usize>() {
+                        Ok(value) => threads = value.clamp(1, max_threads()),
+                        Err(_) => println!("info string Threads has to be a number"),
+                    }
+                }
+
+                if let Some(value) = option_value(&words, "UCI_Chess960") {
+                    chess960 = value == "true";
+                }
+            }
+            Some("ucinewgame") => {
+                stop_search(&mut running);
+                board = Board::start();
+                played.clear();
+                table.clear();
+            }
+            Some("position") => match parse_position(&words[1..]) {
+                Ok((position, hashes)) => {
+                    board = position;
+                    played = hashes;
+                }
+                Err(error) => println!("info string {}", error),
+            },
+            Some("go") => {
+                stop_search(&mut running);
+
+                let limits = parse_go(&words[1..], board.turn, threads);
+                let infinite = words.contains(&"infinite");
+                let stop = Arc::new(AtomicBool::new(false));
+                let search_stop = stop.clone();
+                let table = table.clone();
+                let played = played.clone();
+
+                running = Some((
+                    stop,
+                    thread::spawn(move || {
+                        let result =
+                            search_with(&board, &played, limits, &table, &search_stop, |result| {
+                                print_info(&board, result, chess960)
+                            });
+
+                        // "go infinite" only answers after stop, even when it found a mate long before
+                        while infinite && !search_stop.load(Ordering::Relaxed) {
+                            thread::sleep(Duration::from_millis(10));
+                        }
+
+                        match result {
+                            Some(result) => println!(
+                                "bestmove {}",
+                                move_to_uci(&board, result.best_move, chess960)
+                            ),
+                            None => println!("bestmove 0000"),
+                        }
+                    }),
+                ));
+            }
+            Some("stop") => stop_search(&mut running),
+            Some("quit") => break,
+            _ => {}
+        }
+    }
+
+    stop_search(&mut running);
+}
+
+// Waits for the bestmove so it's always printed before anything else
+fn stop_search(running: &mut Option<(Arc<AtomicBool>, JoinHandle<()>)>) {
+    if let Some((stop, handle)) = running.take() {
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join();
+    }
+}
+
+// "info depth 8 score cp 35 nodes 120000 pv e2e4", UCI scores are from the side to move
+fn print_info(board: &Board, result: &SearchResult, chess960: bool) {
+    let score = if result.score.abs() >= MATE_THRESHOLD {
+        let moves = (MATE_SCORE - result.score.abs() + 1) / 2;
+        format!("mate {}", moves * result.score.signum())
+    } else {
+        format!("cp {}", result.score)
+    };
+
+    println!(
+        "info depth {} score {} nodes {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        move_to_uci(board, result.best_move, chess960)
+    );
+}
+
+// "setoption name Threads value 4"
+fn option_value<'a>(words: &[&'a str], name: &str) -> Option<&'a str> {
+    let name_at = words.iter().position(|w| *w == "name")?;
+    let value_at = words.iter().position(|w| *w == "value")?;
+
+    if words.get(name_at + 1..value_at)?.join(" ") != name {
+        return None;
+    }
+
+    words.get(value_at + 1).copied()
+}
+
+// "startpos moves e2e4 e7e5" or "fen <six fields> moves ...".
+// Also gives back the hashes of the positions on the way, for repetitions
+fn parse_position(words: &[&str]) -> Result<(Board, Vec<u64>), String> {
+    let moves_at = words
+        .iter()
+        .position(|w| *w == "moves")
+        .unwrap_or(words.len());
+
+    let mut board = match words.first() {
+        Some(&"startpos") => Board::start(),
+        Some(&"fen") => Board::from_fen(&words[1..moves_at].join(" "))?.playable()?,
+        _ => return Err("position needs startpos or fen".to_string()),
+    };
+
+    let mut played = Vec::new();
+
+    for text in words.iter().skip(moves_at + 1) {
+        let mv = parse_move(&board, text)?;
+        played.push(board.hash());
+        board = board.make_move(mv);
+    }
+
+    Ok((board, played))
+}
+
+// "go depth 6", "go movetime 1000", "go wtime 60000 btime 60000 winc 1000 binc 1000"
+// or "go infinite" (until stop)
+fn parse_go(words: &[&str], turn: PieceColor, threads: usize) -> SearchLimits {
+    let number = |name: &str| -> Option<u64> {
+        let i = words.iter().position(|w| *w == name)?;
+        words.get(i + 1)?.parse().ok()
+    };
+
+    let (time_name, increment_name) = match turn {
+        PieceColor::White => ("wtime", "winc"),
+        PieceColor::Black => ("btime", "binc"),
+    };
+
+    let time = match (number("movetime"), number(time_name)) {
+        (Some(movetime), _) => Some(Duration::from_millis(movetime)),
+        (None, Some(remaining)) => Some(time_budget(
+            Duration::from_millis(remaining),
+            Duration::from_millis(number(increment_name).unwrap_or(0)),
+        )),
+        (None, None) => None,
+    };
+
+    SearchLimits {
+        depth: number("depth").map_or(MAX_DEPTH, |depth| depth.clamp(1, MAX_DEPTH as u64) as u8),
+        time,
+        threads,
+    }
+}